@@ -164,7 +164,7 @@ fn main() -> ! {
     };
 
     match bq4050.get_temperature() {
-      Ok(temp) => rprintln!("temp {}", temp),
+      Ok(temp) => rprintln!("temp {}", temp.to_celsius()),
       Err(e) => rprintln!("{:#?}", e),
     };
 
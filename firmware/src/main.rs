@@ -12,12 +12,14 @@ use systick_monotonic::Systick;
 
 use stm32f1xx_hal::{
   gpio::gpioa::PA0,
-  gpio::{Alternate, Edge, ExtiPin, Input, OpenDrain, Output, Pin, PinState, PullDown},
-  i2c::{BlockingI2c, Mode},
-  pac::{I2C1, I2C2, SPI1, TIM2, TIM4},
+  gpio::{Alternate, Cr, Edge, ExtiPin, Input, OpenDrain, Output, Pin, PinState, PullDown},
+  i2c::{self, BlockingI2c, Mode},
+  pac::{I2C1, I2C2, SPI1, TIM2, TIM3, TIM4},
   prelude::*,
+  rcc::Clocks,
   spi::{NoMiso, Spi, Spi1NoRemap},
   timer::CounterMs,
+  timer::DelayUs,
   timer::Timer,
 };
 
@@ -35,27 +37,81 @@ use mipidsi::{models::ST7789, Builder, Display};
 
 use ina3221::INA3221;
 
+use graphics::batteries::Batteries;
+
 use peripherals::bq4050;
-use peripherals::bq4050::BQ4050;
+use peripherals::bq4050::{
+  BatterySnapshot, BusErrorKind, RetryPolicy, SnapshotField, SnapshotFields, SnapshotPoller, BQ4050,
+};
+use peripherals::i2c_recovery::BusRecovery;
+
+type Bq4050Pins = (
+  Pin<'B', 10, Alternate<OpenDrain>>,
+  Pin<'B', 11, Alternate<OpenDrain>>,
+);
+
+// Gauge NACKs while busy with its own measurements, a couple of quick retries hide that from the UI
+const BQ4050_RETRY: RetryPolicy = RetryPolicy::new(bq4050_backoff)
+  .retries(2)
+  .backoff(500, 2_000);
+
+// Values read from the gauge, a few of them every data timer tick
+const BQ4050_POLLER: SnapshotPoller = SnapshotPoller::new(
+  SnapshotFields::NONE
+    .with(SnapshotField::Voltage)
+    .with(SnapshotField::Current)
+    .with(SnapshotField::StateOfCharge)
+    .with(SnapshotField::CellVoltages)
+    .with(SnapshotField::Temperature)
+    .with(SnapshotField::BatteryStatus),
+);
+
+// Cell voltages take four reads, so a tick makes up to 5 reads plus the balancing status
+const BQ4050_FIELDS_PER_TICK: usize = 2;
+
+// Core runs at 72 MHz
+fn bq4050_backoff(us: u32) {
+  cortex_m::asm::delay(us * 72);
+}
+
+fn classify_i2c_error(e: &i2c::Error) -> BusErrorKind {
+  match e {
+    i2c::Error::Acknowledge => BusErrorKind::Nack,
+    i2c::Error::Timeout => BusErrorKind::Timeout,
+    _ => BusErrorKind::Other,
+  }
+}
 
 #[pre_init]
 unsafe fn preinit() -> () {
   // TODO - disable baclkight
 }
 
-#[app(device = stm32f1xx_hal::pac, peripherals = true)]
+#[app(device = stm32f1xx_hal::pac, peripherals = true, dispatchers = [USART1])]
 mod app {
   use super::*;
 
   static mut FMT_BUF: [u8; 64] = [0u8; 64];
 
+  #[derive(Copy, Clone)]
   pub struct InaValues {
     bus: [f32; 3],
     volt: [f32; 3],
   }
 
+  #[derive(Copy, Clone)]
   pub struct BqValues {
     temp: f32,
+    cells: [f32; 4],
+    balancing: [bool; 4],
+  }
+
+  // Everything needed to clear and re-initialize I2C2 when the gauge hangs the bus
+  pub struct Bq4050Bus {
+    recovery: BusRecovery,
+    crh: Cr<'B', true>,
+    clocks: Clocks,
+    delay: DelayUs<TIM3>,
   }
 
   pub struct DisplayRedrawLocations {
@@ -71,15 +127,12 @@ mod app {
   #[local]
   struct Local {
     data_timer: CounterMs<TIM4>,
-    bq4050: BQ4050<
-      BlockingI2c<
-        I2C2,
-        (
-          Pin<'B', 10, Alternate<OpenDrain>>,
-          Pin<'B', 11, Alternate<OpenDrain>>,
-        ),
-      >,
-    >,
+    // Taken out only while the bus is being recovered
+    bq4050: Option<BQ4050<BlockingI2c<I2C2, Bq4050Pins>>>,
+    bq4050_bus: Bq4050Bus,
+    bq4050_snapshot: BatterySnapshot<i2c::Error>,
+    // Seconds since boot, counted by the sensor poll
+    uptime: u32,
     ina3221: INA3221<
       BlockingI2c<
         I2C1,
@@ -103,6 +156,7 @@ mod app {
     >,
     redraw_timer: CounterMs<TIM2>,
     fills: DisplayRedrawLocations,
+    batteries: Batteries,
   }
 
   #[monotonic(binds = SysTick, default = true)]
@@ -174,6 +228,9 @@ mod app {
     // Clear the display initially
     display.clear(Rgb565::RED).unwrap();
 
+    let batteries = Batteries::new(Point::new(0, 200), 240);
+    batteries.draw_static(&mut display).unwrap();
+
     rprintln!("Display init finished");
 
     // INA PB6 PB7
@@ -206,24 +263,42 @@ mod app {
     rprintln!("INA3221 init finished");
 
     rprintln!("BQ4050 init");
-    // look into https://github.com/cs2dsb/i2c_hung_fix.rs/blob/master/src/lib.rs for freeze fix
     let pb10 = gpiob.pb10.into_alternate_open_drain(&mut gpiob.crh);
     let pb11 = gpiob.pb11.into_alternate_open_drain(&mut gpiob.crh);
 
-    let i2c2 = BlockingI2c::i2c2(
-      cx.device.I2C2,
-      (pb10, pb11),
-      Mode::Standard {
-        frequency: 100.kHz(),
-      },
-      clocks,
-      20,
-      1,
-      20,
-      20,
-    );
-
-    let bq4050 = bq4050::BQ4050::new(i2c2);
+    let i2c2 = bq4050_i2c(cx.device.I2C2, (pb10, pb11), clocks);
+
+    let mut bq4050 = bq4050::BQ4050::new(i2c2);
+    bq4050.set_error_classifier(classify_i2c_error);
+    bq4050.set_retry_policy(BQ4050_RETRY);
+    // Gauge sits next to the boost converter, reject corrupted replies if it supports PEC
+    match bq4050.detect_pec() {
+      Ok(pec) => rprintln!("BQ4050 PEC: {}", pec),
+      Err(e) => rprintln!("{:#?}", e),
+    };
+
+    match bq4050.get_pack_identity() {
+      Ok(pack) => rprintln!(
+        "Pack {} {} ({}) #{} made {}",
+        pack.manufacturer_name,
+        pack.device_name,
+        pack.device_chemistry,
+        pack.serial_number,
+        pack.manufacture_date
+      ),
+      Err(e) => rprintln!("{:#?}", e),
+    };
+
+    match bq4050.get_gauge_version() {
+      Ok(version) => rprintln!(
+        "Gauge {:04x} fw {} hw {:04x} chem {:04x}",
+        version.device_type,
+        version.firmware,
+        version.hardware_version,
+        version.chem_id
+      ),
+      Err(e) => rprintln!("{:#?}", e),
+    };
     rprintln!("BQ4050 init finished");
 
     let mut data_timer = cx.device.TIM4.counter_ms(&clocks);
@@ -245,10 +320,23 @@ mod app {
           bus: [0.0; 3],
           volt: [0.0; 3],
         },
-        bq: BqValues { temp: 0.0 },
+        bq: BqValues {
+          temp: 0.0,
+          cells: [0.0; 4],
+          balancing: [false; 4],
+        },
       },
       Local {
-        bq4050,
+        bq4050: Some(bq4050),
+        bq4050_bus: Bq4050Bus {
+          // Gauge is polled once a second, give it a few seconds before touching the bus
+          recovery: BusRecovery::new(3),
+          crh: gpiob.crh,
+          clocks,
+          delay,
+        },
+        bq4050_snapshot: BatterySnapshot::default(),
+        uptime: 0,
         ina3221,
         data_timer,
         redraw_timer,
@@ -261,6 +349,7 @@ mod app {
             MonoTextStyle::new(&FONT_9X18_BOLD, Rgb565::WHITE),
           ),
         },
+        batteries,
       },
       init::Monotonics(mono),
     )
@@ -276,61 +365,189 @@ mod app {
     }
   }
 
-  #[task(binds = TIM4, shared = [ina, bq], local = [bq4050, ina3221, data_timer])]
+  #[task(binds = TIM4, local = [data_timer])]
   fn data_timer_update(cx: data_timer_update::Context) {
-    let ina3221 = cx.local.ina3221;
-    let bq4050 = cx.local.bq4050;
+    // Transfers and retry backoff run in the software task, the interrupt only schedules it
+    if poll_sensors::spawn().is_err() {
+      rprintln!("Sensor poll still running, tick skipped");
+    }
+
     let timer = cx.local.data_timer;
+    let int = timer.get_interrupt();
+    timer.clear_interrupt(int);
+  }
 
-    (cx.shared.bq, cx.shared.ina).lock(|bq, ina| {
-      match ina3221.bus_voltage(ina3221::Channel::Ch1) {
-        Ok(v) => ina.bus[0] = v,
-        Err(e) => rprintln!("{:#?}", e),
-      };
-
-      match ina3221.bus_voltage(ina3221::Channel::Ch2) {
-        Ok(v) => ina.bus[1] = v,
-        Err(e) => rprintln!("{:#?}", e),
-      };
-
-      match ina3221.bus_voltage(ina3221::Channel::Ch3) {
-        Ok(v) => ina.bus[2] = v,
-        Err(e) => rprintln!("{:#?}", e),
-      };
-
-      match ina3221.shunt_voltage(ina3221::Channel::Ch1) {
-        Ok(v) => ina.volt[0] = v,
-        Err(e) => rprintln!("{:#?}", e),
-      };
-
-      match ina3221.shunt_voltage(ina3221::Channel::Ch2) {
-        Ok(v) => ina.volt[1] = v,
-        Err(e) => rprintln!("{:#?}", e),
-      };
-
-      match ina3221.shunt_voltage(ina3221::Channel::Ch3) {
-        Ok(v) => ina.volt[2] = v,
-        Err(e) => rprintln!("{:#?}", e),
-      };
-
-      match bq4050.get_temperature() {
-        Ok(temp) => bq.temp = temp,
-        Err(e) => rprintln!("{:#?}", e),
-      };
+  // Reads into copies of the shared values and publishes them at the end,
+  // the redraw task is never blocked by the bus.
+  #[task(shared = [ina, bq], local = [bq4050, bq4050_bus, bq4050_snapshot, uptime, ina3221])]
+  fn poll_sensors(mut cx: poll_sensors::Context) {
+    let ina3221 = cx.local.ina3221;
+    let bq4050 = cx.local.bq4050.as_mut().unwrap();
+    let bus = cx.local.bq4050_bus;
+    let snapshot = cx.local.bq4050_snapshot;
+    let uptime = cx.local.uptime;
+    *uptime += 1;
+    let mut bus_error = false;
+
+    let mut ina = cx.shared.ina.lock(|ina| *ina);
+    let mut bq = cx.shared.bq.lock(|bq| *bq);
+
+    match ina3221.bus_voltage(ina3221::Channel::Ch1) {
+      Ok(v) => ina.bus[0] = v,
+      Err(e) => rprintln!("{:#?}", e),
+    };
+
+    match ina3221.bus_voltage(ina3221::Channel::Ch2) {
+      Ok(v) => ina.bus[1] = v,
+      Err(e) => rprintln!("{:#?}", e),
+    };
+
+    match ina3221.bus_voltage(ina3221::Channel::Ch3) {
+      Ok(v) => ina.bus[2] = v,
+      Err(e) => rprintln!("{:#?}", e),
+    };
+
+    match ina3221.shunt_voltage(ina3221::Channel::Ch1) {
+      Ok(v) => ina.volt[0] = v,
+      Err(e) => rprintln!("{:#?}", e),
+    };
+
+    match ina3221.shunt_voltage(ina3221::Channel::Ch2) {
+      Ok(v) => ina.volt[1] = v,
+      Err(e) => rprintln!("{:#?}", e),
+    };
+
+    match ina3221.shunt_voltage(ina3221::Channel::Ch3) {
+      Ok(v) => ina.volt[2] = v,
+      Err(e) => rprintln!("{:#?}", e),
+    };
+
+    if !BQ4050_POLLER.poll_next(bq4050, snapshot, *uptime, BQ4050_FIELDS_PER_TICK) {
+      for e in snapshot.errors_at(*uptime) {
+        rprintln!("{:#?}", e);
+      }
+    }
+
+    if let Some(temp) = snapshot.temperature.fresh(*uptime, 0) {
+      bq.temp = temp.to_celsius();
+    }
+
+    if let Some(cells) = snapshot.cell_voltages.fresh(*uptime, 0) {
+      for (value, cell) in bq.cells.iter_mut().zip(cells) {
+        *value = cell.to_volts();
+      }
+    }
+
+    match bq4050.get_cell_balancing() {
+      Ok(balancing) => bq.balancing = balancing,
+      Err(e) => {
+        bus_error |= is_bus_error(&e);
+        rprintln!("{:#?}", e)
+      }
+    };
+
+    match snapshot.battery_status.fresh(*uptime, 0) {
+      Some(status) if status.has_alarm() => {
+        rprintln!("BQ4050 alarm: {:?}", status);
+
+        match bq4050.get_safety_status() {
+          Ok(safety) if !safety.is_empty() => rprintln!("BQ4050 safety: {}", safety),
+          Ok(_) => {}
+          Err(e) => {
+            bus_error |= is_bus_error(&e);
+            rprintln!("{:#?}", e)
+          }
+        };
+      }
+      _ => {}
+    };
+
+    (cx.shared.bq, cx.shared.ina).lock(|shared_bq, shared_ina| {
+      *shared_bq = bq;
+      *shared_ina = ina;
     });
 
-    let int = timer.get_interrupt();
-    timer.clear_interrupt(int);
+    bus_error |= snapshot.errors_at(*uptime).any(is_bus_error);
+    if bus.recovery.record(bus_error) {
+      let bq4050 = cx.local.bq4050.take().unwrap();
+      *cx.local.bq4050 = Some(recover_bq4050(bq4050, bus));
+    }
+  }
+
+  fn bq4050_i2c(i2c2: I2C2, pins: Bq4050Pins, clocks: Clocks) -> BlockingI2c<I2C2, Bq4050Pins> {
+    BlockingI2c::i2c2(
+      i2c2,
+      pins,
+      Mode::Standard {
+        frequency: 100.kHz(),
+      },
+      clocks,
+      20,
+      1,
+      20,
+      20,
+    )
+  }
+
+  // Timeouts and arbitration loss mean something holds the bus, NACK is a regular reply of a busy gauge
+  fn is_bus_error(e: &bq4050::Error<i2c::Error>) -> bool {
+    matches!(
+      e,
+      bq4050::Error::Timeout(_)
+        | bq4050::Error::I2cError(i2c::Error::Arbitration | i2c::Error::Bus)
+    )
+  }
+
+  // Clears the bus by bit-banging the pins and brings I2C2 up again
+  fn recover_bq4050(
+    bq4050: BQ4050<BlockingI2c<I2C2, Bq4050Pins>>,
+    bus: &mut Bq4050Bus,
+  ) -> BQ4050<BlockingI2c<I2C2, Bq4050Pins>> {
+    // Driver settings are lost with the rebuilt instance
+    let pec = bq4050.pec_enabled();
+    let fet_control = bq4050.fet_control_allowed();
+    let repeated_start = bq4050.repeated_start_enabled();
+    let classify = bq4050.error_classifier();
+    let retry = bq4050.retry_policy();
+    let (i2c2, (scl, sda)) = bq4050.release().release();
+
+    let mut scl = scl.into_open_drain_output(&mut bus.crh);
+    let mut sda = sda.into_open_drain_output(&mut bus.crh);
+    // Stuck bus is counted by `BusRecovery`, which waits for more bus errors before the next attempt
+    let state = match bus.recovery.recover(&mut scl, &mut sda, &mut bus.delay) {
+      Ok(true) => "released",
+      Ok(false) => "still stuck",
+      Err(_) => "pin error",
+    };
+    rprintln!(
+      "BQ4050 bus recovery #{}: SDA {} ({} stuck so far)",
+      bus.recovery.recoveries(),
+      state,
+      bus.recovery.stuck()
+    );
+
+    let scl = scl.into_alternate_open_drain(&mut bus.crh);
+    let sda = sda.into_alternate_open_drain(&mut bus.crh);
+
+    let mut bq4050 = BQ4050::new(bq4050_i2c(i2c2, (scl, sda), bus.clocks));
+    bq4050.set_pec(pec);
+    bq4050.allow_fet_control(fet_control);
+    bq4050.set_repeated_start(repeated_start);
+    bq4050.set_error_classifier(classify);
+    bq4050.set_retry_policy(retry);
+    bq4050
   }
 
   #[derive(Default)]
   struct DrawData {
     pack_temp: f32,
+    cells: [f32; 4],
+    balancing: [bool; 4],
     bus: [f32; 3],
     volt: [f32; 3],
   }
 
-  #[task(priority = 2, binds = TIM2, shared = [ina, bq], local = [display, redraw_timer, fills])]
+  #[task(priority = 2, binds = TIM2, shared = [ina, bq], local = [display, redraw_timer, fills, batteries])]
   fn redraw_timer_update(cx: redraw_timer_update::Context) {
     let display = cx.local.display;
     let fills = cx.local.fills;
@@ -344,8 +561,18 @@ mod app {
       }
 
       draw_data.pack_temp = bq.temp;
+      draw_data.cells = bq.cells;
+      draw_data.balancing = bq.balancing;
     });
 
+    let batteries = cx.local.batteries;
+    batteries.set_voltage1(draw_data.cells[0]);
+    batteries.set_voltage2(draw_data.cells[1]);
+    batteries.set_voltage3(draw_data.cells[2]);
+    batteries.set_voltage4(draw_data.cells[3]);
+    batteries.set_balancing(draw_data.balancing);
+    batteries.draw(display).unwrap();
+
     fills.temp.draw(display).unwrap();
 
     let buf = unsafe { &mut FMT_BUF };
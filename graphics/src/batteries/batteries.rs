@@ -102,6 +102,15 @@ impl Batteries {
 
     self
   }
+
+  pub fn set_balancing(&mut self, balancing: [bool; 4]) -> &Self {
+    self.b1.set_balancing(balancing[0]);
+    self.b2.set_balancing(balancing[1]);
+    self.b3.set_balancing(balancing[2]);
+    self.b4.set_balancing(balancing[3]);
+
+    self
+  }
 }
 
 impl View for Batteries {
@@ -15,12 +15,13 @@ use embedded_layout::{
   View,
 };
 
-use super::consts::{ANODE_SIZE, BORDER_COLOR, BORDER_SIZE, FONT};
+use super::consts::{ANODE_SIZE, BALANCING_COLOR, BORDER_COLOR, BORDER_SIZE, FONT};
 
 use crate::utils::float_to_fixed;
 
 pub struct Battery {
   voltage: f32,
+  balancing: bool,
   bounds: Rectangle,
   background_color: Rgb565,
 }
@@ -40,6 +41,7 @@ impl Battery {
       bounds,
       background_color,
       voltage: 0.0,
+      balancing: false,
     }
   }
 
@@ -47,26 +49,8 @@ impl Battery {
     &self,
     target: &mut D,
   ) -> Result<&Self, D::Error> {
-    let anode_style = PrimitiveStyleBuilder::new()
-      .fill_color(BORDER_COLOR)
-      .build();
-
-    let anode_rect = Rectangle::new(Point::zero(), ANODE_SIZE).align_to(
-      &self.bounds,
-      horizontal::Left,
-      vertical::Center,
-    );
-
-    RoundedRectangle::new(
-      anode_rect,
-      embedded_graphics::primitives::CornerRadii {
-        top_left: Size::new_equal(2),
-        bottom_left: Size::new_equal(2),
-        top_right: Size::zero(),
-        bottom_right: Size::zero(),
-      },
-    )
-    .draw_styled(&anode_style, target)?;
+    let anode_rect = self.anode_rect();
+    self.draw_anode(BORDER_COLOR, target)?;
 
     let border_style = PrimitiveStyleBuilder::new()
       .stroke_width(BORDER_SIZE)
@@ -94,6 +78,40 @@ impl Battery {
 
     self
   }
+
+  /// Highlights the anode while the cell is being bled
+  pub fn set_balancing(&mut self, balancing: bool) -> &Self {
+    self.balancing = balancing;
+
+    self
+  }
+
+  fn anode_rect(&self) -> Rectangle {
+    Rectangle::new(Point::zero(), ANODE_SIZE).align_to(
+      &self.bounds,
+      horizontal::Left,
+      vertical::Center,
+    )
+  }
+
+  fn draw_anode<D: DrawTarget<Color = Rgb565>>(
+    &self,
+    color: Rgb565,
+    target: &mut D,
+  ) -> Result<(), D::Error> {
+    let anode_style = PrimitiveStyleBuilder::new().fill_color(color).build();
+
+    RoundedRectangle::new(
+      self.anode_rect(),
+      embedded_graphics::primitives::CornerRadii {
+        top_left: Size::new_equal(2),
+        bottom_left: Size::new_equal(2),
+        top_right: Size::zero(),
+        bottom_right: Size::zero(),
+      },
+    )
+    .draw_styled(&anode_style, target)
+  }
 }
 
 impl View for Battery {
@@ -144,6 +162,13 @@ impl<'a> Drawable for Battery {
     )
     .draw(target)?;
 
+    let anode_color = if self.balancing {
+      BALANCING_COLOR
+    } else {
+      BORDER_COLOR
+    };
+    self.draw_anode(anode_color, target)?;
+
     Ok(())
   }
 }
@@ -0,0 +1,442 @@
+use embedded_hal_async::i2c::{self, ErrorKind, I2c};
+
+use super::regs::{self, BlockReg, FlagsReg, MacFlagsReg, WordReg};
+use super::smbus::{self, BLOCK_READ_MAX, BLOCK_WRITE_MAX};
+use super::{
+  capacity_unit, Address, BatteryMode, BatteryStatus, BusErrorKind, Capacity, CapacityUnit,
+  ChargingStatus, Cmd, CmdBlock, DaStatus1, DaStatus2, DeciKelvin, Error, GaugingStatus, MacCmd,
+  MilliAmps, MilliVolts, Minutes, OperationStatus, Percent, PfFlags, SafetyFlags, SecurityMode,
+  BLOCK_MAX, MAC_BLOCK_MAX,
+};
+
+// Async variant of the driver for the values polled periodically.
+// Framing and register decoders are shared with the blocking `BQ4050`, see its methods for command details.
+// Only measurements, status flags and DAStatus are covered. Configuration, security, data flash,
+// authentication, calibration and FET control are left to the blocking `BQ4050`.
+// embedded-hal 1.0 errors report their kind, NACKs are told apart without a classifier.
+// Reads are not retried, `BQ4050::set_retry_policy` busy waits which has no place in async code.
+
+pub struct BQ4050Async<I2C> {
+  i2c: I2C,
+  pec: bool,
+  // Cached BatteryMode()[CAPM], read on the first capacity access
+  capacity_unit: Option<CapacityUnit>,
+  repeated_start: bool,
+}
+
+impl<I2C, I2cError> BQ4050Async<I2C>
+where
+  I2C: I2c<Error = I2cError>,
+  I2cError: i2c::Error,
+{
+  pub fn new(i2c: I2C) -> BQ4050Async<I2C> {
+    BQ4050Async {
+      i2c,
+      pec: false,
+      capacity_unit: None,
+      repeated_start: false,
+    }
+  }
+
+  /// Enables SMBus packet error checking.
+  /// Reads then always use repeated start as the CRC covers the command write too.
+  pub fn set_pec(&mut self, enabled: bool) {
+    self.pec = enabled;
+  }
+
+  pub fn pec_enabled(&self) -> bool {
+    self.pec
+  }
+
+  /// Reads with repeated start instead of separate write and read transactions, see `BQ4050::set_repeated_start`
+  pub fn set_repeated_start(&mut self, enabled: bool) {
+    self.repeated_start = enabled;
+  }
+
+  pub fn repeated_start_enabled(&self) -> bool {
+    self.repeated_start
+  }
+
+  /// Destroys the driver and returns the I2C bus.
+  pub fn release(self) -> I2C {
+    self.i2c
+  }
+
+  // Wraps an error of the HAL
+  fn bus_error(error: I2cError) -> Error<I2cError> {
+    Error::I2cError(error).classify(|e| match e.kind() {
+      ErrorKind::NoAcknowledge(_) => BusErrorKind::Nack,
+      _ => BusErrorKind::Other,
+    })
+  }
+
+  async fn read_word(&mut self, cmd: u8) -> Result<u16, Error<I2cError>> {
+    let mut buffer = [0u8; 3];
+    let read = smbus::word_read_len(self.pec);
+    self.command_read(cmd, &mut buffer[..read]).await?;
+
+    smbus::decode_word(self.pec, cmd, &buffer)
+  }
+
+  async fn write_word(&mut self, cmd: u8, value: u16) -> Result<(), Error<I2cError>> {
+    let mut buffer = [0u8; 4];
+    let len = smbus::encode_word(self.pec, cmd, value, &mut buffer);
+
+    self
+      .i2c
+      .write(Address::Dev as u8, &buffer[..len])
+      .await
+      .map_err(Self::bus_error)
+  }
+
+  async fn read_block_raw(&mut self, cmd: u8, buf: &mut [u8]) -> Result<usize, Error<I2cError>> {
+    let mut block = [0u8; BLOCK_READ_MAX];
+    let read = smbus::block_read_len(self.pec, buf.len());
+    self.command_read(cmd, &mut block[..read]).await?;
+
+    smbus::decode_block(self.pec, cmd, &block[..read], buf)
+  }
+
+  // Same transfer as `BQ4050::command_read`
+  async fn command_read(&mut self, cmd: u8, buffer: &mut [u8]) -> Result<(), Error<I2cError>> {
+    let address = Address::Dev as u8;
+    if self.repeated_start || self.pec {
+      self
+        .i2c
+        .write_read(address, &[cmd], buffer)
+        .await
+        .map_err(Self::bus_error)
+    } else {
+      self
+        .i2c
+        .write(address, &[cmd])
+        .await
+        .map_err(Self::bus_error)?;
+      self
+        .i2c
+        .read(address, buffer)
+        .await
+        .map_err(Self::bus_error)
+    }
+  }
+
+  async fn write_block_raw(&mut self, cmd: u8, data: &[u8]) -> Result<(), Error<I2cError>> {
+    let mut buffer = [0u8; BLOCK_WRITE_MAX];
+    let len = smbus::encode_block(self.pec, cmd, data, &mut buffer)?;
+
+    self
+      .i2c
+      .write(Address::Dev as u8, &buffer[..len])
+      .await
+      .map_err(Self::bus_error)
+  }
+
+  /// Reads SMBus block into `buf` honoring the length byte. Returns the length reported by the device.
+  pub async fn read_block(
+    &mut self,
+    cmd: CmdBlock,
+    buf: &mut [u8],
+  ) -> Result<usize, Error<I2cError>> {
+    self.read_block_raw(cmd as u8, buf).await
+  }
+
+  async fn read_block_at_least(
+    &mut self,
+    cmd: CmdBlock,
+    min: usize,
+  ) -> Result<[u8; BLOCK_MAX], Error<I2cError>> {
+    let mut buffer = [0u8; BLOCK_MAX];
+    let len = self.read_block(cmd, &mut buffer).await?;
+    if len < min {
+      return Err(Error::InvalidLength(len as u8));
+    }
+
+    Ok(buffer)
+  }
+
+  async fn read_block_u32(&mut self, cmd: CmdBlock) -> Result<u32, Error<I2cError>> {
+    let mut buffer = [0u8; 4];
+    let len = self.read_block(cmd, &mut buffer).await?;
+    if len != buffer.len() {
+      return Err(Error::InvalidLength(len as u8));
+    }
+
+    Ok(u32::from_le_bytes(buffer))
+  }
+
+  /// Issues MAC subcommand and copies its reply into `buf`. Returns amount of data bytes copied.
+  pub async fn mac_read(
+    &mut self,
+    subcommand: impl Into<u16>,
+    buf: &mut [u8],
+  ) -> Result<usize, Error<I2cError>> {
+    let subcommand = subcommand.into();
+    self.mac_write(subcommand, &[]).await?;
+
+    let mut block = [0u8; MAC_BLOCK_MAX + 2];
+    let len = self.read_block_raw(Address::Mac as u8, &mut block).await?;
+    smbus::decode_mac(subcommand, &block[..len], buf)
+  }
+
+  pub async fn mac_write(
+    &mut self,
+    subcommand: impl Into<u16>,
+    data: &[u8],
+  ) -> Result<(), Error<I2cError>> {
+    let mut buffer = [0u8; MAC_BLOCK_MAX + 2];
+    let len = smbus::encode_mac(subcommand.into(), data, &mut buffer)?;
+    self
+      .write_block_raw(Address::Mac as u8, &buffer[..len])
+      .await
+  }
+
+  async fn mac_read_flags(&mut self, cmd: MacCmd) -> Result<u32, Error<I2cError>> {
+    let mut buffer = [0u8; 4];
+    let len = self.mac_read(cmd, &mut buffer).await?;
+    smbus::decode_flags(&buffer, len)
+  }
+
+  async fn read_word_reg<T>(&mut self, reg: &WordReg<T>) -> Result<T, Error<I2cError>> {
+    Ok((reg.decode)(self.read_word(reg.cmd as u8).await?))
+  }
+
+  async fn read_flags_reg<T>(&mut self, reg: &FlagsReg<T>) -> Result<T, Error<I2cError>> {
+    Ok((reg.decode)(self.read_block_u32(reg.cmd).await?))
+  }
+
+  async fn read_block_reg<T>(&mut self, reg: &BlockReg<T>) -> Result<T, Error<I2cError>> {
+    let block = self.read_block_at_least(reg.cmd, reg.min).await?;
+    Ok((reg.decode)(&block))
+  }
+
+  async fn read_mac_flags_reg<T>(&mut self, reg: &MacFlagsReg<T>) -> Result<T, Error<I2cError>> {
+    Ok((reg.decode)(self.mac_read_flags(reg.cmd).await?))
+  }
+
+  pub async fn get_battery_mode(&mut self) -> Result<BatteryMode, Error<I2cError>> {
+    self.read_word_reg(&regs::BATTERY_MODE).await
+  }
+
+  pub async fn set_battery_mode(&mut self, mode: BatteryMode) -> Result<(), Error<I2cError>> {
+    self
+      .write_word(Cmd::BatteryModeReg as u8, mode.into())
+      .await?;
+    self.capacity_unit = Some(capacity_unit(&mode));
+    Ok(())
+  }
+
+  pub async fn get_capacity_unit(&mut self) -> Result<CapacityUnit, Error<I2cError>> {
+    match self.capacity_unit {
+      Some(unit) => Ok(unit),
+      None => {
+        let unit = capacity_unit(&self.get_battery_mode().await?);
+        self.capacity_unit = Some(unit);
+        Ok(unit)
+      }
+    }
+  }
+
+  pub fn invalidate_capacity_unit(&mut self) {
+    self.capacity_unit = None;
+  }
+
+  async fn read_capacity(&mut self, cmd: Cmd) -> Result<Capacity, Error<I2cError>> {
+    let unit = self.get_capacity_unit().await?;
+    Ok(Capacity::new(unit, self.read_word(cmd as u8).await?))
+  }
+
+  pub async fn get_temperature(&mut self) -> Result<DeciKelvin, Error<I2cError>> {
+    self.read_word_reg(&regs::TEMPERATURE).await
+  }
+
+  pub async fn get_voltage(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    self.read_word_reg(&regs::VOLTAGE).await
+  }
+
+  pub async fn get_current(&mut self) -> Result<MilliAmps, Error<I2cError>> {
+    self.read_word_reg(&regs::CURRENT).await
+  }
+
+  pub async fn get_average_current(&mut self) -> Result<MilliAmps, Error<I2cError>> {
+    self.read_word_reg(&regs::AVERAGE_CURRENT).await
+  }
+
+  pub async fn get_relative_state_of_charge(&mut self) -> Result<Percent, Error<I2cError>> {
+    self.read_word_reg(&regs::RELATIVE_SOC).await
+  }
+
+  pub async fn get_absolute_state_of_charge(&mut self) -> Result<Percent, Error<I2cError>> {
+    self.read_word_reg(&regs::ABSOLUTE_SOC).await
+  }
+
+  pub async fn get_remaining_capacity(&mut self) -> Result<Capacity, Error<I2cError>> {
+    self.read_capacity(Cmd::RemainingCapacityReg).await
+  }
+
+  pub async fn get_full_charge_capacity(&mut self) -> Result<Capacity, Error<I2cError>> {
+    self.read_capacity(Cmd::FullChargeCapacityReg).await
+  }
+
+  pub async fn get_run_time_to_empty(&mut self) -> Result<Option<Minutes>, Error<I2cError>> {
+    self.read_word_reg(&regs::RUN_TIME_TO_EMPTY).await
+  }
+
+  pub async fn get_average_time_to_empty(&mut self) -> Result<Option<Minutes>, Error<I2cError>> {
+    self.read_word_reg(&regs::AVERAGE_TIME_TO_EMPTY).await
+  }
+
+  pub async fn get_average_time_to_full(&mut self) -> Result<Option<Minutes>, Error<I2cError>> {
+    self.read_word_reg(&regs::AVERAGE_TIME_TO_FULL).await
+  }
+
+  pub async fn get_battery_status(&mut self) -> Result<BatteryStatus, Error<I2cError>> {
+    self.read_word_reg(&regs::BATTERY_STATUS).await
+  }
+
+  pub async fn get_cycle_count(&mut self) -> Result<u16, Error<I2cError>> {
+    self.read_word_reg(&regs::CYCLE_COUNT).await
+  }
+
+  pub async fn get_cell_voltage_1(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    self.read_word_reg(&regs::CELL_VOLTAGE_1).await
+  }
+
+  pub async fn get_cell_voltage_2(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    self.read_word_reg(&regs::CELL_VOLTAGE_2).await
+  }
+
+  pub async fn get_cell_voltage_3(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    self.read_word_reg(&regs::CELL_VOLTAGE_3).await
+  }
+
+  pub async fn get_cell_voltage_4(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    self.read_word_reg(&regs::CELL_VOLTAGE_4).await
+  }
+
+  pub async fn get_safety_alert(&mut self) -> Result<SafetyFlags, Error<I2cError>> {
+    self.read_flags_reg(&regs::SAFETY_ALERT).await
+  }
+
+  pub async fn get_safety_status(&mut self) -> Result<SafetyFlags, Error<I2cError>> {
+    self.read_flags_reg(&regs::SAFETY_STATUS).await
+  }
+
+  pub async fn get_pf_alert(&mut self) -> Result<PfFlags, Error<I2cError>> {
+    self.read_flags_reg(&regs::PF_ALERT).await
+  }
+
+  pub async fn get_pf_status(&mut self) -> Result<PfFlags, Error<I2cError>> {
+    self.read_flags_reg(&regs::PF_STATUS).await
+  }
+
+  pub async fn get_operation_status(&mut self) -> Result<OperationStatus, Error<I2cError>> {
+    self.read_mac_flags_reg(&regs::OPERATION_STATUS).await
+  }
+
+  pub async fn security_mode(&mut self) -> Result<SecurityMode, Error<I2cError>> {
+    Ok(self.get_operation_status().await?.security_mode)
+  }
+
+  pub async fn get_charging_status(&mut self) -> Result<ChargingStatus, Error<I2cError>> {
+    self.read_mac_flags_reg(&regs::CHARGING_STATUS).await
+  }
+
+  pub async fn get_gauging_status(&mut self) -> Result<GaugingStatus, Error<I2cError>> {
+    self.read_mac_flags_reg(&regs::GAUGING_STATUS).await
+  }
+
+  pub async fn get_da_status1(&mut self) -> Result<DaStatus1, Error<I2cError>> {
+    self.read_block_reg(&regs::DA_STATUS1).await
+  }
+
+  pub async fn get_da_status2(&mut self) -> Result<DaStatus2, Error<I2cError>> {
+    self.read_block_reg(&regs::DA_STATUS2).await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bq4050::{SecurityMode, BQ4050};
+  use crate::mock::{block_on, MockError, MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+  const MAC: u8 = Address::Mac as u8;
+  const TEMPERATURE: u8 = Cmd::TemperatureReg as u8;
+
+  #[test]
+  fn read_word_transfer() {
+    let mut bq4050 = BQ4050Async::new(MockI2c::new(&[
+      Transaction::write(DEV, &[TEMPERATURE]),
+      Transaction::read(DEV, &[0x94, 0x0B]),
+      Transaction::write_read(DEV, &[TEMPERATURE], &[0x94, 0x0B]),
+      Transaction::write_read(DEV, &[TEMPERATURE], &[0x94, 0x0B, 0xF9]),
+    ]));
+
+    assert_eq!(
+      block_on(bq4050.get_temperature()).unwrap(),
+      DeciKelvin(0x0B94)
+    );
+    bq4050.set_repeated_start(true);
+    assert_eq!(
+      block_on(bq4050.get_temperature()).unwrap(),
+      DeciKelvin(0x0B94)
+    );
+    bq4050.set_repeated_start(false);
+    bq4050.set_pec(true);
+    assert_eq!(
+      block_on(bq4050.get_temperature()).unwrap(),
+      DeciKelvin(0x0B94)
+    );
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn same_transactions_as_blocking() {
+    let script = [
+      Transaction::write(DEV, &[MAC, 2, 0x54, 0x00]),
+      Transaction::write(DEV, &[MAC]),
+      Transaction::read(DEV, &[6, 0x54, 0x00, 0x00, 0x03, 0x00, 0x00]),
+    ];
+
+    let mut blocking = BQ4050::new(MockI2c::new(&script));
+    let mut asynch = BQ4050Async::new(MockI2c::new(&script));
+    assert_eq!(blocking.security_mode().unwrap(), SecurityMode::Sealed);
+    assert_eq!(
+      block_on(asynch.security_mode()).unwrap(),
+      SecurityMode::Sealed
+    );
+    blocking.release().done();
+    asynch.release().done();
+  }
+
+  #[test]
+  fn nack_is_classified() {
+    let mut bq4050 = BQ4050Async::new(MockI2c::new(&[Transaction::Fail(MockError::Nack)]));
+
+    assert!(matches!(
+      block_on(bq4050.get_voltage()),
+      Err(Error::Nack(MockError::Nack))
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn battery_mode_cached_after_write() {
+    let mode = Cmd::BatteryModeReg as u8;
+    let mut bq4050 = BQ4050Async::new(MockI2c::new(&[
+      Transaction::Fail(MockError::Nack),
+      Transaction::write(DEV, &[mode]),
+      Transaction::read(DEV, &[0x00, 0x00]),
+    ]));
+
+    let capm = BatteryMode::from(0x8000);
+    assert!(block_on(bq4050.set_battery_mode(capm)).is_err());
+    // Failed write leaves the cache empty, the unit is read from the gauge
+    assert_eq!(
+      block_on(bq4050.get_capacity_unit()).unwrap(),
+      CapacityUnit::MilliAmpHours
+    );
+    bq4050.release().done();
+  }
+}
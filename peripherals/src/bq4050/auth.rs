@@ -0,0 +1,293 @@
+use byteorder::{BigEndian, ByteOrder};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+use super::{Error, MacCmd, BQ4050};
+
+// SHA-1 authentication, see 13.34 Authenticate() and 13.1 AuthenticationKey().
+// Host writes a 160-bit challenge, the gauge answers with SHA-1(K || SHA-1(K || M)),
+// where K is the 128-bit authentication key and M is the challenge.
+// TI default key is 0x0123456789ABCDEFFEDCBA9876543210.
+
+pub const AUTH_KEY_LEN: usize = 16;
+pub const AUTH_CHALLENGE_LEN: usize = 20;
+pub const AUTH_RESPONSE_LEN: usize = 20;
+
+// Time the gauge needs to calculate the response or store a new key
+const AUTH_DELAY_US: u32 = 250_000;
+
+impl<I2C, I2cError> BQ4050<I2C>
+where
+  I2C: WriteRead<Error = I2cError> + Write<Error = I2cError> + Read<Error = I2cError>,
+{
+  /// Sends random `challenge` and checks that the gauge response matches `key`
+  pub fn authenticate(
+    &mut self,
+    key: &[u8; AUTH_KEY_LEN],
+    challenge: &[u8; AUTH_CHALLENGE_LEN],
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<bool, Error<I2cError>> {
+    self.send_challenge(challenge)?;
+    delay_source.delay_us(AUTH_DELAY_US);
+    let response = self.read_auth_response()?;
+
+    Ok(response == auth_response(key, challenge))
+  }
+
+  /// Replaces the authentication key. Gauge must be UNSEALED
+  pub fn set_authentication_key(
+    &mut self,
+    key: &[u8; AUTH_KEY_LEN],
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<(), Error<I2cError>> {
+    self.mac_write(MacCmd::AuthenticationKey, &[])?;
+    delay_source.delay_us(AUTH_DELAY_US);
+    self.send_challenge(key)?;
+    delay_source.delay_us(AUTH_DELAY_US);
+    Ok(())
+  }
+}
+
+/// Response a genuine gauge with `key` gives to `challenge`
+pub fn auth_response(
+  key: &[u8; AUTH_KEY_LEN],
+  challenge: &[u8; AUTH_CHALLENGE_LEN],
+) -> [u8; AUTH_RESPONSE_LEN] {
+  let mut inner = Sha1::new();
+  inner.update(key);
+  inner.update(challenge);
+  let inner = inner.finish();
+
+  let mut outer = Sha1::new();
+  outer.update(key);
+  outer.update(&inner);
+  outer.finish()
+}
+
+/// Minimal SHA-1 (FIPS 180-4)
+pub struct Sha1 {
+  state: [u32; 5],
+  block: [u8; 64],
+  block_len: usize,
+  total_len: u64,
+}
+
+impl Sha1 {
+  pub fn new() -> Self {
+    Sha1 {
+      state: [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0],
+      block: [0u8; 64],
+      block_len: 0,
+      total_len: 0,
+    }
+  }
+
+  pub fn update(&mut self, mut data: &[u8]) {
+    self.total_len += data.len() as u64;
+
+    while !data.is_empty() {
+      let take = (64 - self.block_len).min(data.len());
+      self.block[self.block_len..self.block_len + take].copy_from_slice(&data[..take]);
+      self.block_len += take;
+      data = &data[take..];
+
+      if self.block_len == 64 {
+        self.compress();
+        self.block_len = 0;
+      }
+    }
+  }
+
+  pub fn finish(mut self) -> [u8; 20] {
+    let bit_len = self.total_len * 8;
+
+    self.block[self.block_len] = 0x80;
+    self.block[self.block_len + 1..].fill(0);
+    if self.block_len >= 56 {
+      self.compress();
+      self.block.fill(0);
+    }
+    BigEndian::write_u64(&mut self.block[56..], bit_len);
+    self.compress();
+
+    let mut digest = [0u8; 20];
+    BigEndian::write_u32_into(&self.state, &mut digest);
+    digest
+  }
+
+  fn compress(&mut self) {
+    let mut w = [0u32; 80];
+    BigEndian::read_u32_into(&self.block, &mut w[..16]);
+    for i in 16..80 {
+      w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = self.state;
+    for (i, word) in w.iter().enumerate() {
+      let (f, k) = match i {
+        0..=19 => ((b & c) | (!b & d), 0x5A827999),
+        20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+        40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+        _ => (b ^ c ^ d, 0xCA62C1D6),
+      };
+
+      let temp = a
+        .rotate_left(5)
+        .wrapping_add(f)
+        .wrapping_add(e)
+        .wrapping_add(k)
+        .wrapping_add(*word);
+      e = d;
+      d = c;
+      c = b.rotate_left(30);
+      b = a;
+      a = temp;
+    }
+
+    for (state, value) in self.state.iter_mut().zip([a, b, c, d, e]) {
+      *state = state.wrapping_add(value);
+    }
+  }
+}
+
+impl Default for Sha1 {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::vec::Vec;
+
+  use super::*;
+  use crate::bq4050::{Address, CmdBlock};
+  use crate::mock::{MockDelay, MockI2c, Transaction};
+
+  // TI default key, see 13.1 AuthenticationKey()
+  const DEFAULT_KEY: [u8; AUTH_KEY_LEN] = [
+    0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, 0xFE, 0xDC, 0xBA, 0x98, 0x76, 0x54, 0x32, 0x10,
+  ];
+
+  // Expected digests are calculated with Python hashlib
+  fn hex(digest: &[u8]) -> std::string::String {
+    digest
+      .iter()
+      .map(|byte| std::format!("{:02x}", byte))
+      .collect()
+  }
+
+  fn sha1(data: &[u8]) -> std::string::String {
+    let mut sha = Sha1::new();
+    sha.update(data);
+    hex(&sha.finish())
+  }
+
+  fn challenge() -> [u8; AUTH_CHALLENGE_LEN] {
+    core::array::from_fn(|i| i as u8)
+  }
+
+  #[test]
+  fn sha1_fips_vectors() {
+    assert_eq!(sha1(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    assert_eq!(sha1(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    assert_eq!(
+      sha1(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+      "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+    );
+  }
+
+  #[test]
+  fn sha1_padding() {
+    // 55 bytes fit the length into the same block, 56 need another one, 64 fill the block exactly
+    let cases = [
+      (55, "c1c8bbdc22796e28c0e15163d20899b65621d65a"),
+      (56, "c2db330f6083854c99d4b5bfb6e8f29f201be699"),
+      (63, "03f09f5b158a7a8cdad920bddc29b81c18a551f5"),
+      (64, "0098ba824b5c16427bd7a1122a5a442a25ec644d"),
+      (65, "11655326c708d70319be2610e8a57d9a5b959d3b"),
+    ];
+
+    for (len, digest) in cases {
+      assert_eq!(sha1(&[b'a'; 65][..len]), digest, "length {}", len);
+    }
+  }
+
+  #[test]
+  fn sha1_split_updates() {
+    let data = std::vec![b'a'; 1_000_000];
+    let mut sha = Sha1::new();
+    for chunk in data.chunks(7) {
+      sha.update(chunk);
+    }
+
+    assert_eq!(
+      hex(&sha.finish()),
+      "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+    );
+  }
+
+  // The TRM gives the default key but no worked challenge and response,
+  // so the expected response is calculated with Python hashlib as
+  // sha1(K + sha1(K + M)) for K = default key and M = 00 01 .. 13
+  #[test]
+  fn response_with_default_key() {
+    assert_eq!(
+      hex(&auth_response(&DEFAULT_KEY, &challenge())),
+      "b78043012bb40f9ff86404f645c2ab6a9f28eaf3"
+    );
+  }
+
+  #[test]
+  fn authenticate_checks_response() {
+    let dev = Address::Dev as u8;
+    let cmd = CmdBlock::AuthenticateReg as u8;
+
+    let mut write = Vec::from([cmd, AUTH_CHALLENGE_LEN as u8]);
+    write.extend_from_slice(&challenge());
+    let mut reply = Vec::from([AUTH_RESPONSE_LEN as u8]);
+    reply.extend_from_slice(&auth_response(&DEFAULT_KEY, &challenge()));
+    let mut wrong = reply.clone();
+    wrong[1] ^= 0x01;
+
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[
+      Transaction::write(dev, &write),
+      Transaction::write(dev, &[cmd]),
+      Transaction::read(dev, &reply),
+      Transaction::write(dev, &write),
+      Transaction::write(dev, &[cmd]),
+      Transaction::read(dev, &wrong),
+    ]));
+    let mut delay = MockDelay::default();
+
+    assert!(bq4050
+      .authenticate(&DEFAULT_KEY, &challenge(), &mut delay)
+      .unwrap());
+    assert!(!bq4050
+      .authenticate(&DEFAULT_KEY, &challenge(), &mut delay)
+      .unwrap());
+    assert_eq!(delay.total_us, 2 * AUTH_DELAY_US);
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn set_authentication_key_sequence() {
+    let dev = Address::Dev as u8;
+    let mac = Address::Mac as u8;
+    let cmd = CmdBlock::AuthenticateReg as u8;
+    let new_key: [u8; AUTH_KEY_LEN] = core::array::from_fn(|i| 0xA0 + i as u8);
+
+    let mut write = Vec::from([cmd, AUTH_KEY_LEN as u8]);
+    write.extend_from_slice(&new_key);
+
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[
+      Transaction::write(dev, &[mac, 2, 0x37, 0x00]),
+      Transaction::write(dev, &write),
+    ]));
+    let mut delay = MockDelay::default();
+
+    bq4050.set_authentication_key(&new_key, &mut delay).unwrap();
+    assert_eq!(delay.total_us, 2 * AUTH_DELAY_US);
+    bq4050.release().done();
+  }
+}
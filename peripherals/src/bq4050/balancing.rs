@@ -0,0 +1,25 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+// 13.1.x CBStatus() layout.
+// Minimal length of the block, replies can be padded by the gauge.
+pub(super) const CB_STATUS_LEN: usize = 8;
+
+/// CBStatus() calculated cell balancing time
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CbStatus {
+  /// Remaining balancing time per cell in seconds
+  pub balance_time: [u16; 4],
+}
+
+impl CbStatus {
+  pub(super) fn from_block(data: &[u8]) -> Self {
+    CbStatus {
+      balance_time: [0, 1, 2, 3].map(|i| LittleEndian::read_u16(&data[i * 2..i * 2 + 2])),
+    }
+  }
+
+  /// Cells being bled. `active` is OperationStatus()[CB], time is kept while balancing is paused
+  pub fn balancing(&self, active: bool) -> [bool; 4] {
+    self.balance_time.map(|time| active && time > 0)
+  }
+}
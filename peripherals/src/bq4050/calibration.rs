@@ -0,0 +1,410 @@
+use byteorder::{ByteOrder, LittleEndian};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+use super::flash::{
+  DfParam, DF_BAT_GAIN, DF_CAPACITY_GAIN, DF_CC_GAIN, DF_CC_OFFSET, DF_CC_OFFSET_SAMPLES,
+  DF_CELL_GAIN, DF_EXTERNAL1_TEMP_OFFSET, DF_EXTERNAL2_TEMP_OFFSET, DF_INTERNAL_TEMP_OFFSET,
+  DF_PACK_GAIN,
+};
+use super::{DeciKelvin, DfValue, Error, MacCmd, MilliAmps, MilliVolts, BQ4050};
+
+// Calibration, see 13.1 CalibrationMode() and OutputCCandADCforCalibration().
+// While the output is enabled ManufacturerBlockAccess() returns raw counts, refreshed every 250 ms.
+// Gauge must be UNSEALED to store the results in data flash.
+// Sampling enables CalibrationMode() by itself and restores the previous ManufacturingStatus()[CAL_EN] afterwards.
+
+// Refresh period of calibration output
+const CAL_SAMPLE_US: u32 = 250_000;
+// Ratio of Capacity Gain to CC Gain
+const CAPACITY_GAIN_FACTOR: f32 = 298_261.62;
+
+/// Raw counts reported by OutputCCandADCforCalibration()
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CalibrationData {
+  /// Incremented on every new conversion
+  pub counter: u8,
+  pub status: u8,
+  pub cc: i16,
+  pub cell_voltage: [i16; 4],
+  pub pack_voltage: i16,
+  pub bat_voltage: i16,
+}
+
+impl CalibrationData {
+  fn from_block(data: &[u8]) -> Self {
+    let word = |i: usize| LittleEndian::read_i16(&data[2 + i * 2..4 + i * 2]);
+
+    CalibrationData {
+      counter: data[0],
+      status: data[1],
+      cc: word(0),
+      cell_voltage: [1, 2, 3, 4].map(word),
+      pack_voltage: word(5),
+      bat_voltage: word(6),
+    }
+  }
+}
+
+/// Counts averaged over several conversions
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CalibrationAverage {
+  pub cc: f32,
+  pub cell_voltage: [f32; 4],
+  pub pack_voltage: f32,
+  pub bat_voltage: f32,
+}
+
+/// Temperature sensor with an offset in data flash
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TemperatureSensor {
+  Internal,
+  External1,
+  External2,
+}
+
+impl TemperatureSensor {
+  fn offset_param(&self) -> &'static DfParam {
+    match self {
+      TemperatureSensor::Internal => &DF_INTERNAL_TEMP_OFFSET,
+      TemperatureSensor::External1 => &DF_EXTERNAL1_TEMP_OFFSET,
+      TemperatureSensor::External2 => &DF_EXTERNAL2_TEMP_OFFSET,
+    }
+  }
+}
+
+impl<I2C, I2cError> BQ4050<I2C>
+where
+  I2C: WriteRead<Error = I2cError> + Write<Error = I2cError> + Read<Error = I2cError>,
+{
+  /// Enables or disables calibration mode, ManufacturingStatus()[CAL_EN]
+  pub fn set_calibration_mode(&mut self, enabled: bool) -> Result<(), Error<I2cError>> {
+    if self.get_manufacturing_status()?.cal_en != enabled {
+      self.mac_write(MacCmd::CalibrationMode, &[])?;
+    }
+    Ok(())
+  }
+
+  /// Reads the latest raw counts. `shorted` selects OutputShortedCCandADCforCalibration(),
+  /// which reports CC with the SRP and SRN inputs shorted internally
+  pub fn read_calibration_data(
+    &mut self,
+    shorted: bool,
+  ) -> Result<CalibrationData, Error<I2cError>> {
+    let cmd = if shorted {
+      MacCmd::OutputShortedCcAndAdcForCalibration
+    } else {
+      MacCmd::OutputCcAndAdcForCalibration
+    };
+
+    let mut buffer = [0u8; 16];
+    let len = self.mac_read(cmd, &mut buffer)?;
+    if len < buffer.len() {
+      return Err(Error::InvalidLength(len as u8));
+    }
+
+    Ok(CalibrationData::from_block(&buffer))
+  }
+
+  /// Averages `samples` conversions in calibration mode.
+  /// Calibration output is stopped and CAL_EN restored afterwards, even if sampling failed.
+  pub fn sample_calibration(
+    &mut self,
+    shorted: bool,
+    samples: u16,
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<CalibrationAverage, Error<I2cError>> {
+    if samples == 0 {
+      return Err(Error::InvalidValue);
+    }
+
+    let cal_en = self.get_manufacturing_status()?.cal_en;
+    if !cal_en {
+      self.mac_write(MacCmd::CalibrationMode, &[])?;
+    }
+
+    let average = self.average_calibration(shorted, samples, delay_source);
+    let exit = self.mac_write(MacCmd::ExitCalibrationOutput, &[]);
+    let restore = if cal_en {
+      Ok(())
+    } else {
+      self.set_calibration_mode(false)
+    };
+
+    let average = average?;
+    exit?;
+    restore?;
+    Ok(average)
+  }
+
+  fn average_calibration(
+    &mut self,
+    shorted: bool,
+    samples: u16,
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<CalibrationAverage, Error<I2cError>> {
+    let mut average = CalibrationAverage::default();
+
+    for _ in 0..samples {
+      delay_source.delay_us(CAL_SAMPLE_US);
+      let data = self.read_calibration_data(shorted)?;
+
+      average.cc += data.cc as f32;
+      for (sum, counts) in average.cell_voltage.iter_mut().zip(data.cell_voltage) {
+        *sum += counts as f32;
+      }
+      average.pack_voltage += data.pack_voltage as f32;
+      average.bat_voltage += data.bat_voltage as f32;
+    }
+
+    let samples = samples as f32;
+    average.cc /= samples;
+    average.cell_voltage = average.cell_voltage.map(|sum| sum / samples);
+    average.pack_voltage /= samples;
+    average.bat_voltage /= samples;
+
+    Ok(average)
+  }
+
+  /// Calibrates Cell Gain. `reference` holds measured voltages of the connected cells, starting with cell 1
+  pub fn calibrate_cell_voltage(
+    &mut self,
+    reference: &[MilliVolts],
+    samples: u16,
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<i64, Error<I2cError>> {
+    if reference.is_empty() || reference.len() > 4 {
+      return Err(Error::InvalidValue);
+    }
+
+    let average = self.sample_calibration(false, samples, delay_source)?;
+    let mut gain = 0.0;
+    for (voltage, counts) in reference.iter().zip(average.cell_voltage) {
+      gain += voltage_gain(*voltage, counts)?;
+    }
+    let gain = gain / reference.len() as f32;
+
+    self.write_gain(&DF_CELL_GAIN, gain)
+  }
+
+  /// Calibrates Pack Gain against voltage measured on PACK+
+  pub fn calibrate_pack_voltage(
+    &mut self,
+    reference: MilliVolts,
+    samples: u16,
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<i64, Error<I2cError>> {
+    let average = self.sample_calibration(false, samples, delay_source)?;
+    self.write_gain(
+      &DF_PACK_GAIN,
+      voltage_gain(reference, average.pack_voltage)?,
+    )
+  }
+
+  /// Calibrates BAT Gain against voltage measured on BAT+
+  pub fn calibrate_bat_voltage(
+    &mut self,
+    reference: MilliVolts,
+    samples: u16,
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<i64, Error<I2cError>> {
+    let average = self.sample_calibration(false, samples, delay_source)?;
+    self.write_gain(&DF_BAT_GAIN, voltage_gain(reference, average.bat_voltage)?)
+  }
+
+  /// Calibrates CC Offset. No current may flow through the sense resistor
+  pub fn calibrate_cc_offset(
+    &mut self,
+    samples: u16,
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<i64, Error<I2cError>> {
+    let offset_samples = self.cc_offset_samples()?;
+    let average = self.sample_calibration(true, samples, delay_source)?;
+    let offset = round(average.cc * offset_samples);
+
+    self.write_param(&DF_CC_OFFSET, DfValue::Integer(offset))?;
+    Ok(offset)
+  }
+
+  /// Calibrates CC Gain and Capacity Gain against a known constant current.
+  /// Run `calibrate_cc_offset` first
+  pub fn calibrate_current(
+    &mut self,
+    reference: MilliAmps,
+    samples: u16,
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<f32, Error<I2cError>> {
+    let offset_samples = self.cc_offset_samples()?;
+    let offset = match self.read_param(&DF_CC_OFFSET)? {
+      DfValue::Integer(offset) => offset as f32 / offset_samples,
+      DfValue::Float(offset) => offset / offset_samples,
+    };
+
+    let average = self.sample_calibration(false, samples, delay_source)?;
+    let counts = average.cc - offset;
+    if counts == 0.0 || reference.0 == 0 {
+      return Err(Error::InvalidValue);
+    }
+    let gain = reference.0 as f32 / counts;
+
+    self.write_param(&DF_CC_GAIN, DfValue::Float(gain))?;
+    self.write_param(
+      &DF_CAPACITY_GAIN,
+      DfValue::Float(gain * CAPACITY_GAIN_FACTOR),
+    )?;
+    Ok(gain)
+  }
+
+  /// Adjusts temperature offset so that the sensor reports `reference`
+  pub fn calibrate_temperature(
+    &mut self,
+    sensor: TemperatureSensor,
+    reference: DeciKelvin,
+  ) -> Result<i64, Error<I2cError>> {
+    let temperatures = self.get_da_status2()?;
+    let measured = match sensor {
+      TemperatureSensor::Internal => temperatures.internal_temperature,
+      TemperatureSensor::External1 => temperatures.ts1_temperature,
+      TemperatureSensor::External2 => temperatures.ts2_temperature,
+    };
+
+    let param = sensor.offset_param();
+    let offset = match self.read_param(param)? {
+      DfValue::Integer(offset) => offset,
+      DfValue::Float(_) => return Err(Error::InvalidValue),
+    };
+    let offset = offset + reference.0 as i64 - measured.0 as i64;
+
+    self.write_param(param, DfValue::Integer(offset))?;
+    Ok(offset)
+  }
+
+  fn write_gain(&mut self, param: &DfParam, gain: f32) -> Result<i64, Error<I2cError>> {
+    let gain = round(gain);
+    self.write_param(param, DfValue::Integer(gain))?;
+    Ok(gain)
+  }
+
+  // CC Offset is stored as the sum of Coulomb Counter Offset Samples conversions
+  fn cc_offset_samples(&mut self) -> Result<f32, Error<I2cError>> {
+    match self.read_param(&DF_CC_OFFSET_SAMPLES)? {
+      DfValue::Integer(samples) if samples > 0 => Ok(samples as f32),
+      _ => Err(Error::InvalidValue),
+    }
+  }
+}
+
+// Voltage gains scale ADC counts to mV in 1/65536 units.
+// No counts means nothing is connected, zero gain would break voltage measurement.
+fn voltage_gain<I2cError>(reference: MilliVolts, counts: f32) -> Result<f32, Error<I2cError>> {
+  if counts <= 0.0 || reference.0 == 0 {
+    return Err(Error::InvalidValue);
+  }
+  Ok(reference.0 as f32 * 65536.0 / counts)
+}
+
+fn round(value: f32) -> i64 {
+  if value < 0.0 {
+    (value - 0.5) as i64
+  } else {
+    (value + 0.5) as i64
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bq4050::Address;
+  use crate::mock::{MockDelay, MockError, MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+  const MAC: u8 = Address::Mac as u8;
+
+  fn mac_cmd(cmd: MacCmd) -> Transaction {
+    let [low, high] = (cmd as u16).to_le_bytes();
+    Transaction::write(DEV, &[MAC, 2, low, high])
+  }
+
+  // ManufacturingStatus() read with CAL_EN set to `cal_en`
+  fn manufacturing_status(cal_en: bool) -> [Transaction; 3] {
+    let high = if cal_en { 0x80 } else { 0x00 };
+    [
+      mac_cmd(MacCmd::ManufacturingStatus),
+      Transaction::write(DEV, &[MAC]),
+      Transaction::read(DEV, &[4, 0x57, 0x00, 0x00, high]),
+    ]
+  }
+
+  #[test]
+  fn zero_counts_are_rejected() {
+    assert!(matches!(
+      voltage_gain::<()>(MilliVolts(3600), 0.0),
+      Err(Error::InvalidValue)
+    ));
+    assert!(matches!(
+      voltage_gain::<()>(MilliVolts(3600), -1.0),
+      Err(Error::InvalidValue)
+    ));
+    assert_eq!(
+      voltage_gain::<()>(MilliVolts(4000), 65536.0).unwrap(),
+      4000.0
+    );
+  }
+
+  #[test]
+  fn zero_samples_are_rejected() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[]));
+    let mut delay = MockDelay::default();
+
+    assert!(matches!(
+      bq4050.calibrate_pack_voltage(MilliVolts(16000), 0, &mut delay),
+      Err(Error::InvalidValue)
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn output_is_stopped_after_failure() {
+    let mut script = std::vec::Vec::new();
+    script.extend(manufacturing_status(false));
+    script.push(mac_cmd(MacCmd::CalibrationMode));
+    script.push(Transaction::Fail(MockError::Nack));
+    script.push(mac_cmd(MacCmd::ExitCalibrationOutput));
+    script.extend(manufacturing_status(true));
+    script.push(mac_cmd(MacCmd::CalibrationMode));
+
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script));
+    let mut delay = MockDelay::default();
+
+    assert!(matches!(
+      bq4050.sample_calibration(false, 4, &mut delay),
+      Err(Error::I2cError(MockError::Nack))
+    ));
+    bq4050.release().done();
+  }
+
+  // Coulomb Counter Offset Samples read with value `samples`
+  fn offset_samples(samples: u16) -> [Transaction; 3] {
+    let [low, high] = samples.to_le_bytes();
+    [
+      Transaction::write(DEV, &[MAC, 2, 0x10, 0x40]),
+      Transaction::write(DEV, &[MAC]),
+      Transaction::read(DEV, &[4, 0x10, 0x40, low, high]),
+    ]
+  }
+
+  #[test]
+  fn cc_offset_samples_from_data_flash() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(
+      &[offset_samples(64), offset_samples(0)].concat(),
+    ));
+
+    assert_eq!(bq4050.cc_offset_samples().unwrap(), 64.0);
+    // Nothing is sampled with a broken sample count
+    assert!(matches!(
+      bq4050.calibrate_cc_offset(4, &mut MockDelay::default()),
+      Err(Error::InvalidValue)
+    ));
+    bq4050.release().done();
+  }
+}
@@ -0,0 +1,208 @@
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+use super::{Error, MacCmd, BQ4050};
+
+// Device control MAC commands, see 13.1 ShutdownMode(), SleepMode(), CHGFET(), DSGFET(), Gauging(),
+// FETControl() and DeviceReset().
+// FET, gauging and FET control commands toggle the state, so current ManufacturingStatus() is checked first.
+
+impl<I2C, I2cError> BQ4050<I2C>
+where
+  I2C: WriteRead<Error = I2cError> + Write<Error = I2cError> + Read<Error = I2cError>,
+{
+  /// Allows manual FET toggles. Disabled by default, meant for bring-up only
+  pub fn allow_fet_control(&mut self, allowed: bool) {
+    self.fet_control_allowed = allowed;
+  }
+
+  pub fn fet_control_allowed(&self) -> bool {
+    self.fet_control_allowed
+  }
+
+  /// Enables or disables firmware control of the FETs, ManufacturingStatus()[FET_EN]
+  pub fn set_fet_control(&mut self, enabled: bool) -> Result<(), Error<I2cError>> {
+    self.check_fet_control()?;
+
+    if self.get_manufacturing_status()?.fet_en != enabled {
+      self.mac_write(MacCmd::FetControl, &[])?;
+    }
+    Ok(())
+  }
+
+  /// Turns CHG FET on or off. Takes effect only while firmware FET control is disabled
+  pub fn set_charge_fet(&mut self, on: bool) -> Result<(), Error<I2cError>> {
+    self.check_fet_control()?;
+
+    if self.get_manufacturing_status()?.chg_test != on {
+      self.mac_write(MacCmd::ChgFet, &[])?;
+    }
+    Ok(())
+  }
+
+  /// Turns DSG FET on or off. Takes effect only while firmware FET control is disabled
+  pub fn set_discharge_fet(&mut self, on: bool) -> Result<(), Error<I2cError>> {
+    self.check_fet_control()?;
+
+    if self.get_manufacturing_status()?.dsg_test != on {
+      self.mac_write(MacCmd::DsgFet, &[])?;
+    }
+    Ok(())
+  }
+
+  /// Enables or disables Impedance Track gauging, ManufacturingStatus()[GAUGE_EN]
+  pub fn set_gauging(&mut self, enabled: bool) -> Result<(), Error<I2cError>> {
+    if self.get_manufacturing_status()?.gauge_en != enabled {
+      self.mac_write(MacCmd::Gauging, &[])?;
+    }
+    Ok(())
+  }
+
+  /// Enters SHUTDOWN (ship) mode. Command is sent twice as required in SEALED mode,
+  /// the gauge wakes up once charger voltage is applied to PACK
+  pub fn shutdown(&mut self) -> Result<(), Error<I2cError>> {
+    self.mac_write(MacCmd::ShutdownMode, &[])?;
+    self.mac_write(MacCmd::ShutdownMode, &[])
+  }
+
+  /// Enters SLEEP mode if sleep conditions are met
+  pub fn sleep(&mut self) -> Result<(), Error<I2cError>> {
+    self.mac_write(MacCmd::SleepMode, &[])
+  }
+
+  /// Resets the gauge
+  pub fn reset(&mut self) -> Result<(), Error<I2cError>> {
+    self.mac_write(MacCmd::DeviceReset, &[])?;
+    self.invalidate_capacity_unit();
+    Ok(())
+  }
+
+  fn check_fet_control(&self) -> Result<(), Error<I2cError>> {
+    if self.fet_control_allowed {
+      Ok(())
+    } else {
+      Err(Error::FetControlNotAllowed)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bq4050::{Address, CapacityUnit, Cmd};
+  use crate::mock::{MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+  const MAC: u8 = Address::Mac as u8;
+
+  // 13.1.x ManufacturingStatus() bits
+  const CHG_TEST: u8 = 1 << 1;
+  const DSG_TEST: u8 = 1 << 2;
+  const GAUGE_EN: u8 = 1 << 3;
+  const FET_EN: u8 = 1 << 4;
+
+  fn mac_cmd(cmd: MacCmd) -> Transaction {
+    let [low, high] = (cmd as u16).to_le_bytes();
+    Transaction::write(DEV, &[MAC, 2, low, high])
+  }
+
+  // ManufacturingStatus() read with low byte `status`
+  fn manufacturing_status(status: u8) -> [Transaction; 3] {
+    [
+      mac_cmd(MacCmd::ManufacturingStatus),
+      Transaction::write(DEV, &[MAC]),
+      Transaction::read(DEV, &[4, 0x57, 0x00, status, 0x00]),
+    ]
+  }
+
+  #[test]
+  fn fet_toggles_need_permission() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[]));
+
+    assert!(!bq4050.fet_control_allowed());
+    assert!(matches!(
+      bq4050.set_fet_control(true),
+      Err(Error::FetControlNotAllowed)
+    ));
+    assert!(matches!(
+      bq4050.set_charge_fet(true),
+      Err(Error::FetControlNotAllowed)
+    ));
+    assert!(matches!(
+      bq4050.set_discharge_fet(true),
+      Err(Error::FetControlNotAllowed)
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn toggles_only_on_change() {
+    let mut script = std::vec::Vec::new();
+    // Already in the requested state, nothing is sent
+    script.extend(manufacturing_status(FET_EN));
+    script.extend(manufacturing_status(CHG_TEST));
+    script.extend(manufacturing_status(DSG_TEST));
+    script.extend(manufacturing_status(GAUGE_EN));
+    // State differs, the toggle follows the status read
+    script.extend(manufacturing_status(FET_EN));
+    script.push(mac_cmd(MacCmd::FetControl));
+    script.extend(manufacturing_status(0));
+    script.push(mac_cmd(MacCmd::ChgFet));
+    script.extend(manufacturing_status(0));
+    script.push(mac_cmd(MacCmd::DsgFet));
+    script.extend(manufacturing_status(GAUGE_EN));
+    script.push(mac_cmd(MacCmd::Gauging));
+
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script));
+    bq4050.allow_fet_control(true);
+
+    bq4050.set_fet_control(true).unwrap();
+    bq4050.set_charge_fet(true).unwrap();
+    bq4050.set_discharge_fet(true).unwrap();
+    bq4050.set_gauging(true).unwrap();
+
+    bq4050.set_fet_control(false).unwrap();
+    bq4050.set_charge_fet(true).unwrap();
+    bq4050.set_discharge_fet(true).unwrap();
+    bq4050.set_gauging(false).unwrap();
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn shutdown_is_sent_twice() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[
+      mac_cmd(MacCmd::ShutdownMode),
+      mac_cmd(MacCmd::ShutdownMode),
+    ]));
+
+    bq4050.shutdown().unwrap();
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn reset_reads_capacity_unit_again() {
+    let mode = Cmd::BatteryModeReg as u8;
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[
+      Transaction::write(DEV, &[mode]),
+      Transaction::read(DEV, &[0x00, 0x80]),
+      mac_cmd(MacCmd::DeviceReset),
+      Transaction::write(DEV, &[mode]),
+      Transaction::read(DEV, &[0x00, 0x00]),
+    ]));
+
+    assert_eq!(
+      bq4050.get_capacity_unit().unwrap(),
+      CapacityUnit::TenMilliWattHours
+    );
+    // Cached, no bus access
+    assert_eq!(
+      bq4050.get_capacity_unit().unwrap(),
+      CapacityUnit::TenMilliWattHours
+    );
+    bq4050.reset().unwrap();
+    assert_eq!(
+      bq4050.get_capacity_unit().unwrap(),
+      CapacityUnit::MilliAmpHours
+    );
+    bq4050.release().done();
+  }
+}
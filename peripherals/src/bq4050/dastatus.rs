@@ -0,0 +1,155 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::{DeciKelvin, MilliAmps, MilliVolts};
+
+// 13.1.x DAStatus1() and DAStatus2() layout.
+// Minimal length of every block, replies can be padded by the gauge.
+pub(super) const DA_STATUS1_LEN: usize = 32;
+pub(super) const DA_STATUS2_LEN: usize = 14;
+
+/// DAStatus1() snapshot of voltages, currents and powers
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DaStatus1 {
+  pub cell_voltage: [MilliVolts; 4],
+  /// Voltage at the BAT pin
+  pub bat_voltage: MilliVolts,
+  /// Voltage at the PACK pin
+  pub pack_voltage: MilliVolts,
+  /// Simultaneous current measured during cell voltage measurement
+  pub cell_current: [MilliAmps; 4],
+  /// Cell power in cW
+  pub cell_power: [i16; 4],
+  /// Power in cW
+  pub power: i16,
+  /// Average power in cW
+  pub average_power: i16,
+}
+
+impl DaStatus1 {
+  pub(super) fn from_block(data: &[u8]) -> Self {
+    let word = |i: usize| LittleEndian::read_u16(&data[i * 2..i * 2 + 2]);
+
+    DaStatus1 {
+      cell_voltage: [0, 1, 2, 3].map(|i| MilliVolts(word(i))),
+      bat_voltage: MilliVolts(word(4)),
+      pack_voltage: MilliVolts(word(5)),
+      cell_current: [6, 7, 8, 9].map(|i| MilliAmps(word(i) as i16)),
+      cell_power: [10, 11, 12, 13].map(|i| word(i) as i16),
+      power: word(14) as i16,
+      average_power: word(15) as i16,
+    }
+  }
+}
+
+/// DAStatus2() snapshot of temperatures
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DaStatus2 {
+  pub internal_temperature: DeciKelvin,
+  pub ts1_temperature: DeciKelvin,
+  pub ts2_temperature: DeciKelvin,
+  pub ts3_temperature: DeciKelvin,
+  pub ts4_temperature: DeciKelvin,
+  pub cell_temperature: DeciKelvin,
+  pub fet_temperature: DeciKelvin,
+}
+
+impl DaStatus2 {
+  pub(super) fn from_block(data: &[u8]) -> Self {
+    let word = |i: usize| DeciKelvin(LittleEndian::read_u16(&data[i * 2..i * 2 + 2]));
+
+    DaStatus2 {
+      internal_temperature: word(0),
+      ts1_temperature: word(1),
+      ts2_temperature: word(2),
+      ts3_temperature: word(3),
+      ts4_temperature: word(4),
+      cell_temperature: word(5),
+      fet_temperature: word(6),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::vec::Vec;
+
+  use super::*;
+  use crate::bq4050::{Address, CmdBlock, Error, BQ4050};
+  use crate::mock::{MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+
+  fn block_read(cmd: CmdBlock, values: &[i16]) -> [Transaction; 2] {
+    let mut reply = Vec::from([values.len() as u8 * 2]);
+    reply.extend(values.iter().flat_map(|value| value.to_le_bytes()));
+    [
+      Transaction::write(DEV, &[cmd as u8]),
+      Transaction::read(DEV, &reply),
+    ]
+  }
+
+  #[test]
+  fn da_status_decode() {
+    let script = [
+      block_read(
+        CmdBlock::DaStatus1Reg,
+        &[
+          3701, 3702, 3703, 3704, 14810, 14790, -1250, -1251, -1252, -1253, -463, -464, -465, -466,
+          -1851, -1700,
+        ],
+      ),
+      block_read(
+        CmdBlock::DaStatus2Reg,
+        &[2982, 2971, 2972, 2973, 2974, 2975, 3001],
+      ),
+    ]
+    .concat();
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script));
+
+    assert_eq!(
+      bq4050.get_da_status1().unwrap(),
+      DaStatus1 {
+        cell_voltage: [3701, 3702, 3703, 3704].map(MilliVolts),
+        bat_voltage: MilliVolts(14810),
+        pack_voltage: MilliVolts(14790),
+        cell_current: [-1250, -1251, -1252, -1253].map(MilliAmps),
+        cell_power: [-463, -464, -465, -466],
+        power: -1851,
+        average_power: -1700,
+      }
+    );
+    assert_eq!(
+      bq4050.get_da_status2().unwrap(),
+      DaStatus2 {
+        internal_temperature: DeciKelvin(2982),
+        ts1_temperature: DeciKelvin(2971),
+        ts2_temperature: DeciKelvin(2972),
+        ts3_temperature: DeciKelvin(2973),
+        ts4_temperature: DeciKelvin(2974),
+        cell_temperature: DeciKelvin(2975),
+        fet_temperature: DeciKelvin(3001),
+      }
+    );
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn short_block_is_rejected() {
+    let script = [
+      block_read(CmdBlock::DaStatus1Reg, &[3701; 15]),
+      block_read(CmdBlock::DaStatus2Reg, &[2982; 6]),
+    ]
+    .concat();
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script));
+
+    assert!(matches!(
+      bq4050.get_da_status1(),
+      Err(Error::InvalidLength(30))
+    ));
+    assert!(matches!(
+      bq4050.get_da_status2(),
+      Err(Error::InvalidLength(12))
+    ));
+    bq4050.release().done();
+  }
+}
@@ -0,0 +1,463 @@
+use byteorder::{ByteOrder, LittleEndian};
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+use super::{Error, SecurityMode, BQ4050, MAC_BLOCK_MAX};
+
+// Data flash is accessed through ManufacturerBlockAccess() with the flash address used as the subcommand.
+// Read returns 32 bytes starting at the address, write accepts up to 32 bytes.
+// Gauge must be UNSEALED, writes to some parameters additionally require FULL ACCESS.
+
+pub const DATA_FLASH_START: u16 = 0x4000;
+pub const DATA_FLASH_END: u16 = 0x5FFF;
+
+/// Data flash value type as listed in the TRM
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DfType {
+  I1,
+  I2,
+  I4,
+  U1,
+  U2,
+  U4,
+  H1,
+  H2,
+  H4,
+  F4,
+}
+
+impl DfType {
+  pub fn size(&self) -> usize {
+    match self {
+      DfType::I1 | DfType::U1 | DfType::H1 => 1,
+      DfType::I2 | DfType::U2 | DfType::H2 => 2,
+      DfType::I4 | DfType::U4 | DfType::H4 | DfType::F4 => 4,
+    }
+  }
+
+  fn decode(&self, raw: &[u8]) -> DfValue {
+    match self {
+      DfType::I1 => DfValue::Integer(raw[0] as i8 as i64),
+      DfType::I2 => DfValue::Integer(LittleEndian::read_i16(raw) as i64),
+      DfType::I4 => DfValue::Integer(LittleEndian::read_i32(raw) as i64),
+      DfType::U1 | DfType::H1 => DfValue::Integer(raw[0] as i64),
+      DfType::U2 | DfType::H2 => DfValue::Integer(LittleEndian::read_u16(raw) as i64),
+      DfType::U4 | DfType::H4 => DfValue::Integer(LittleEndian::read_u32(raw) as i64),
+      DfType::F4 => DfValue::Float(LittleEndian::read_f32(raw)),
+    }
+  }
+
+  fn encode(&self, value: DfValue, raw: &mut [u8]) {
+    match (self, value) {
+      (DfType::F4, DfValue::Float(value)) => LittleEndian::write_f32(raw, value),
+      (_, DfValue::Integer(value)) => match self.size() {
+        1 => raw[0] = value as u8,
+        2 => LittleEndian::write_u16(raw, value as u16),
+        _ => LittleEndian::write_u32(raw, value as u32),
+      },
+      // Rejected by `DfParam::check`
+      (_, DfValue::Float(_)) => {}
+    }
+  }
+}
+
+/// Value of data flash parameter
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DfValue {
+  Integer(i64),
+  Float(f32),
+}
+
+/// Data flash parameter description
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DfParam {
+  pub name: &'static str,
+  pub address: u16,
+  pub kind: DfType,
+  /// Multiplier converting raw value into `units`
+  pub scale: f32,
+  pub units: &'static str,
+  /// Allowed range of raw integer values
+  pub min: i64,
+  pub max: i64,
+}
+
+impl DfParam {
+  const fn new(name: &'static str, address: u16, kind: DfType, units: &'static str) -> Self {
+    let (min, max) = match kind {
+      DfType::I1 => (i8::MIN as i64, i8::MAX as i64),
+      DfType::I2 => (i16::MIN as i64, i16::MAX as i64),
+      DfType::I4 => (i32::MIN as i64, i32::MAX as i64),
+      DfType::U1 | DfType::H1 => (0, u8::MAX as i64),
+      DfType::U2 | DfType::H2 => (0, u16::MAX as i64),
+      DfType::U4 | DfType::H4 => (0, u32::MAX as i64),
+      DfType::F4 => (0, 0),
+    };
+
+    DfParam {
+      name,
+      address,
+      kind,
+      scale: 1.0,
+      units,
+      min,
+      max,
+    }
+  }
+
+  const fn scale(mut self, scale: f32) -> Self {
+    self.scale = scale;
+    self
+  }
+
+  const fn range(mut self, min: i64, max: i64) -> Self {
+    self.min = min;
+    self.max = max;
+    self
+  }
+
+  fn check(&self, value: DfValue) -> bool {
+    match (self.kind, value) {
+      (DfType::F4, DfValue::Float(value)) => value.is_finite(),
+      (DfType::F4, DfValue::Integer(_)) | (_, DfValue::Float(_)) => false,
+      (_, DfValue::Integer(value)) => (self.min..=self.max).contains(&value),
+    }
+  }
+}
+
+// Commonly tuned parameters, addresses follow the Data Flash Summary of the TRM.
+// Layout may move between firmware revisions, compare StaticDFSignature() with a known pack before writing.
+
+// Calibration
+pub const DF_CELL_GAIN: DfParam = DfParam::new("Cell Gain", 0x4000, DfType::I2, "");
+pub const DF_PACK_GAIN: DfParam = DfParam::new("Pack Gain", 0x4002, DfType::U2, "");
+pub const DF_BAT_GAIN: DfParam = DfParam::new("BAT Gain", 0x4004, DfType::U2, "");
+pub const DF_CC_GAIN: DfParam = DfParam::new("CC Gain", 0x4006, DfType::F4, "mΩ");
+pub const DF_CAPACITY_GAIN: DfParam = DfParam::new("Capacity Gain", 0x400A, DfType::F4, "mΩ");
+pub const DF_CC_OFFSET: DfParam = DfParam::new("CC Offset", 0x400E, DfType::I2, "");
+pub const DF_CC_OFFSET_SAMPLES: DfParam =
+  DfParam::new("Coulomb Counter Offset Samples", 0x4010, DfType::U2, "").range(1, 65535);
+pub const DF_BOARD_OFFSET: DfParam = DfParam::new("Board Offset", 0x4012, DfType::I2, "");
+pub const DF_INTERNAL_TEMP_OFFSET: DfParam =
+  DfParam::new("Internal Temp Offset", 0x4014, DfType::I1, "°C").scale(0.1);
+pub const DF_EXTERNAL1_TEMP_OFFSET: DfParam =
+  DfParam::new("External1 Temp Offset", 0x4015, DfType::I1, "°C").scale(0.1);
+pub const DF_EXTERNAL2_TEMP_OFFSET: DfParam =
+  DfParam::new("External2 Temp Offset", 0x4016, DfType::I1, "°C").scale(0.1);
+
+// Settings
+pub const DF_DA_CONFIGURATION: DfParam = DfParam::new("DA Configuration", 0x4617, DfType::H1, "");
+pub const DF_LED_CONFIGURATION: DfParam = DfParam::new("LED Configuration", 0x461D, DfType::H1, "");
+
+// Protections
+pub const DF_CUV_THRESHOLD: DfParam =
+  DfParam::new("CUV Threshold", 0x4687, DfType::I2, "mV").range(0, 5000);
+pub const DF_COV_THRESHOLD_STANDARD: DfParam =
+  DfParam::new("COV Threshold Standard Temp", 0x4690, DfType::I2, "mV").range(0, 5000);
+pub const DF_OCC1_THRESHOLD: DfParam = DfParam::new("OCC1 Threshold", 0x46A5, DfType::I2, "mA");
+pub const DF_OCD1_THRESHOLD: DfParam = DfParam::new("OCD1 Threshold", 0x46B1, DfType::I2, "mA");
+
+// Gas gauging
+pub const DF_DESIGN_CAPACITY_MAH: DfParam =
+  DfParam::new("Design Capacity mAh", 0x4B31, DfType::I2, "mAh").range(0, 32767);
+pub const DF_DESIGN_CAPACITY_CWH: DfParam =
+  DfParam::new("Design Capacity cWh", 0x4B33, DfType::I2, "cWh").range(0, 32767);
+pub const DF_DESIGN_VOLTAGE: DfParam =
+  DfParam::new("Design Voltage", 0x4B35, DfType::I2, "mV").range(0, 32767);
+
+/// All known parameters, for host tools listing the data flash
+pub const DF_PARAMS: [DfParam; 20] = [
+  DF_CELL_GAIN,
+  DF_PACK_GAIN,
+  DF_BAT_GAIN,
+  DF_CC_GAIN,
+  DF_CAPACITY_GAIN,
+  DF_CC_OFFSET,
+  DF_CC_OFFSET_SAMPLES,
+  DF_BOARD_OFFSET,
+  DF_INTERNAL_TEMP_OFFSET,
+  DF_EXTERNAL1_TEMP_OFFSET,
+  DF_EXTERNAL2_TEMP_OFFSET,
+  DF_DA_CONFIGURATION,
+  DF_LED_CONFIGURATION,
+  DF_CUV_THRESHOLD,
+  DF_COV_THRESHOLD_STANDARD,
+  DF_OCC1_THRESHOLD,
+  DF_OCD1_THRESHOLD,
+  DF_DESIGN_CAPACITY_MAH,
+  DF_DESIGN_CAPACITY_CWH,
+  DF_DESIGN_VOLTAGE,
+];
+
+impl<I2C, I2cError> BQ4050<I2C>
+where
+  I2C: WriteRead<Error = I2cError> + Write<Error = I2cError> + Read<Error = I2cError>,
+{
+  /// Reads `buf.len()` bytes of data flash starting at `address`
+  pub fn read_data_flash(&mut self, address: u16, buf: &mut [u8]) -> Result<(), Error<I2cError>> {
+    check_flash_range(address, buf.len())?;
+
+    for (i, chunk) in buf.chunks_mut(MAC_BLOCK_MAX).enumerate() {
+      let chunk_address = address + (i * MAC_BLOCK_MAX) as u16;
+      let len = match self.mac_read(chunk_address, chunk) {
+        Ok(len) => len,
+        Err(error) => return Err(self.sealed_or(error)),
+      };
+      if len < chunk.len() {
+        return Err(Error::InvalidLength(len as u8));
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Writes `data` into data flash starting at `address`
+  pub fn write_data_flash(&mut self, address: u16, data: &[u8]) -> Result<(), Error<I2cError>> {
+    check_flash_range(address, data.len())?;
+
+    for (i, chunk) in data.chunks(MAC_BLOCK_MAX).enumerate() {
+      let chunk_address = address + (i * MAC_BLOCK_MAX) as u16;
+      if let Err(error) = self.mac_write(chunk_address, chunk) {
+        return Err(self.sealed_or(error));
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn read_param(&mut self, param: &DfParam) -> Result<DfValue, Error<I2cError>> {
+    let mut buffer = [0u8; 4];
+    let raw = &mut buffer[..param.kind.size()];
+    self.read_data_flash(param.address, raw)?;
+    Ok(param.kind.decode(raw))
+  }
+
+  /// Writes the parameter after checking that the value matches its type and range
+  pub fn write_param(&mut self, param: &DfParam, value: DfValue) -> Result<(), Error<I2cError>> {
+    if !param.check(value) {
+      return Err(Error::InvalidValue);
+    }
+
+    let mut buffer = [0u8; 4];
+    let raw = &mut buffer[..param.kind.size()];
+    param.kind.encode(value, raw);
+    self.write_data_flash(param.address, raw)
+  }
+
+  // Sealed gauge does not answer data flash access, report that instead of the bus error
+  fn sealed_or(&mut self, error: Error<I2cError>) -> Error<I2cError> {
+    match self.security_mode() {
+      Ok(SecurityMode::Sealed) => Error::Sealed,
+      _ => error,
+    }
+  }
+}
+
+fn check_flash_range<I2cError>(address: u16, len: usize) -> Result<(), Error<I2cError>> {
+  let end = address as usize + len;
+  if address < DATA_FLASH_START || len == 0 || end > DATA_FLASH_END as usize + 1 {
+    return Err(Error::InvalidFlashAddress(address));
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::vec::Vec;
+
+  use super::*;
+  use crate::bq4050::Address;
+  use crate::mock::{MockError, MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+  const MAC: u8 = Address::Mac as u8;
+
+  // Data flash read of `data` at `address`
+  fn read_chunk(address: u16, data: &[u8]) -> [Transaction; 3] {
+    let [low, high] = address.to_le_bytes();
+    let mut reply = Vec::from([data.len() as u8 + 2, low, high]);
+    reply.extend_from_slice(data);
+    [
+      Transaction::write(DEV, &[MAC, 2, low, high]),
+      Transaction::write(DEV, &[MAC]),
+      Transaction::read(DEV, &reply),
+    ]
+  }
+
+  fn write_chunk(address: u16, data: &[u8]) -> Transaction {
+    let [low, high] = address.to_le_bytes();
+    let mut write = Vec::from([MAC, data.len() as u8 + 2, low, high]);
+    write.extend_from_slice(data);
+    Transaction::write(DEV, &write)
+  }
+
+  // OperationStatus() read with SEC1, SEC0 set to `sec`
+  fn operation_status(sec: u8) -> [Transaction; 3] {
+    read_chunk(0x0054, &[0x00, sec, 0x00, 0x00])
+  }
+
+  fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| i as u8).collect()
+  }
+
+  #[test]
+  fn read_splits_into_chunks() {
+    let data = pattern(40);
+    let mut bq4050 = BQ4050::new(MockI2c::new(
+      &[
+        read_chunk(0x4010, &data[..32]),
+        read_chunk(0x4030, &data[32..]),
+      ]
+      .concat(),
+    ));
+
+    let mut buf = [0u8; 40];
+    bq4050.read_data_flash(0x4010, &mut buf).unwrap();
+    assert_eq!(buf.as_slice(), data.as_slice());
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn read_short_chunk() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(
+      &[
+        read_chunk(0x4010, &pattern(32)),
+        read_chunk(0x4030, &pattern(4)),
+      ]
+      .concat(),
+    ));
+
+    let mut buf = [0u8; 40];
+    assert!(matches!(
+      bq4050.read_data_flash(0x4010, &mut buf),
+      Err(Error::InvalidLength(4))
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn write_splits_into_chunks() {
+    let data = pattern(40);
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[
+      write_chunk(0x5FD0, &data[..32]),
+      write_chunk(0x5FF0, &data[32..]),
+    ]));
+
+    bq4050.write_data_flash(0x5FD0, &data).unwrap();
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn flash_range() {
+    let cases = [
+      (0x3FFF, 1, false),
+      (0x4000, 0, false),
+      (0x4000, 1, true),
+      (0x5FE0, 32, true),
+      (0x5FFF, 1, true),
+      (0x5FFF, 2, false),
+      (0x6000, 1, false),
+    ];
+
+    for (address, len, valid) in cases {
+      let result = check_flash_range::<()>(address, len);
+      match valid {
+        true => assert!(result.is_ok(), "{:04x} + {}", address, len),
+        false => assert!(
+          matches!(result, Err(Error::InvalidFlashAddress(a)) if a == address),
+          "{:04x} + {}",
+          address,
+          len
+        ),
+      }
+    }
+  }
+
+  #[test]
+  fn out_of_range_access_is_not_sent() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[]));
+
+    assert!(matches!(
+      bq4050.read_data_flash(0x5FF0, &mut [0u8; 32]),
+      Err(Error::InvalidFlashAddress(0x5FF0))
+    ));
+    assert!(matches!(
+      bq4050.write_data_flash(0x3FF0, &[0u8; 4]),
+      Err(Error::InvalidFlashAddress(0x3FF0))
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn param_values_are_checked() {
+    let invalid = [
+      (DF_CUV_THRESHOLD, DfValue::Integer(-1)),
+      (DF_CUV_THRESHOLD, DfValue::Integer(5001)),
+      (DF_CUV_THRESHOLD, DfValue::Float(2500.0)),
+      (DF_INTERNAL_TEMP_OFFSET, DfValue::Integer(-129)),
+      (DF_DA_CONFIGURATION, DfValue::Integer(0x100)),
+      (DF_CC_GAIN, DfValue::Integer(1)),
+      (DF_CC_GAIN, DfValue::Float(f32::NAN)),
+    ];
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[]));
+
+    for (param, value) in invalid {
+      assert!(
+        matches!(bq4050.write_param(&param, value), Err(Error::InvalidValue)),
+        "{} = {:?}",
+        param.name,
+        value
+      );
+    }
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn param_round_trip() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(
+      &[
+        [write_chunk(0x4687, &[0xC4, 0x09])].as_slice(),
+        &read_chunk(0x4687, &[0xC4, 0x09]),
+        &[write_chunk(0x4014, &[0xFB])],
+        &read_chunk(0x4014, &[0xFB]),
+        &[write_chunk(0x4006, &[0x00, 0x00, 0xC0, 0x3F])],
+        &read_chunk(0x4006, &[0x00, 0x00, 0xC0, 0x3F]),
+      ]
+      .concat(),
+    ));
+
+    let cases = [
+      (DF_CUV_THRESHOLD, DfValue::Integer(2500)),
+      (DF_INTERNAL_TEMP_OFFSET, DfValue::Integer(-5)),
+      (DF_CC_GAIN, DfValue::Float(1.5)),
+    ];
+    for (param, value) in cases {
+      bq4050.write_param(&param, value).unwrap();
+      assert_eq!(bq4050.read_param(&param).unwrap(), value);
+    }
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn sealed_gauge_is_reported() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(
+      &[
+        [Transaction::Fail(MockError::Nack)].as_slice(),
+        &operation_status(0b11),
+        &[Transaction::Fail(MockError::Nack)],
+        &operation_status(0b10),
+      ]
+      .concat(),
+    ));
+
+    assert!(matches!(
+      bq4050.write_data_flash(0x4687, &[0xC4, 0x09]),
+      Err(Error::Sealed)
+    ));
+    assert!(matches!(
+      bq4050.write_data_flash(0x4687, &[0xC4, 0x09]),
+      Err(Error::I2cError(MockError::Nack))
+    ));
+    bq4050.release().done();
+  }
+}
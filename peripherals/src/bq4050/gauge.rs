@@ -0,0 +1,318 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::DeciKelvin;
+
+// 13.1.x GaugeStatus1() - GaugeStatus3(), StateOfHealth() and FilteredCapacity() layout.
+// Minimal length of every block, replies can be padded by the gauge.
+pub(super) const GAUGE_STATUS1_LEN: usize = 32;
+pub(super) const GAUGE_STATUS2_LEN: usize = 32;
+pub(super) const GAUGE_STATUS3_LEN: usize = 32;
+pub(super) const STATE_OF_HEALTH_LEN: usize = 4;
+pub(super) const FILTERED_CAPACITY_LEN: usize = 8;
+
+fn word(data: &[u8], index: usize) -> u16 {
+  LittleEndian::read_u16(&data[index * 2..index * 2 + 2])
+}
+
+/// GaugeStatus1() Impedance Track capacities and resistance scales
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GaugeStatus1 {
+  /// True remaining capacity in mAh
+  pub true_rem_q: u16,
+  /// True remaining energy in cWh
+  pub true_rem_e: u16,
+  /// Initial capacity calculated from IT simulation in mAh
+  pub initial_q: u16,
+  /// Initial energy calculated from IT simulation in cWh
+  pub initial_e: u16,
+  /// True full charge capacity in mAh
+  pub true_full_chg_q: u16,
+  /// True full charge energy in cWh
+  pub true_full_chg_e: u16,
+  /// Temperature during last simulation run
+  pub t_sim: DeciKelvin,
+  /// Current assumed ambient temperature used by the IT algorithm
+  pub t_ambient: DeciKelvin,
+  /// Ra table scaling factor per cell
+  pub ra_scale: [u16; 4],
+  /// Last temperature compensated resistance per cell in mΩ
+  pub comp_res: [u16; 4],
+}
+
+impl GaugeStatus1 {
+  pub(super) fn from_block(data: &[u8]) -> Self {
+    GaugeStatus1 {
+      true_rem_q: word(data, 0),
+      true_rem_e: word(data, 1),
+      initial_q: word(data, 2),
+      initial_e: word(data, 3),
+      true_full_chg_q: word(data, 4),
+      true_full_chg_e: word(data, 5),
+      t_sim: DeciKelvin(word(data, 6)),
+      t_ambient: DeciKelvin(word(data, 7)),
+      ra_scale: [8, 9, 10, 11].map(|i| word(data, i)),
+      comp_res: [12, 13, 14, 15].map(|i| word(data, i)),
+    }
+  }
+}
+
+/// GaugeStatus2() Impedance Track state and DOD0 values
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GaugeStatus2 {
+  /// Active pack grid point
+  pub pack_grid: u8,
+  /// Learned status of resistance table
+  pub lstatus: u8,
+  /// Active grid point per cell
+  pub cell_grid: [u8; 4],
+  /// Time passed since last state change in seconds
+  pub state_time: u32,
+  /// Depth of discharge at last OCV reading per cell
+  pub dod0: [u16; 4],
+  /// Passed capacity since last DOD0 update in mAh
+  pub dod0_passed_q: u16,
+  /// Passed energy since last DOD0 update in cWh
+  pub dod0_passed_e: u16,
+  /// Time passed since last DOD0 update in hours/16
+  pub dod0_time: u16,
+  /// Depth of discharge at end of charge per cell
+  pub dodeoc: [u16; 4],
+}
+
+impl GaugeStatus2 {
+  pub(super) fn from_block(data: &[u8]) -> Self {
+    GaugeStatus2 {
+      pack_grid: data[0],
+      lstatus: data[1],
+      cell_grid: [data[2], data[3], data[4], data[5]],
+      state_time: LittleEndian::read_u32(&data[6..10]),
+      dod0: [5, 6, 7, 8].map(|i| word(data, i)),
+      dod0_passed_q: word(data, 9),
+      dod0_passed_e: word(data, 10),
+      dod0_time: word(data, 11),
+      dodeoc: [12, 13, 14, 15].map(|i| word(data, i)),
+    }
+  }
+}
+
+/// GaugeStatus3() QMax learning values
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GaugeStatus3 {
+  /// Learned maximum chemical capacity per cell in mAh
+  pub qmax: [u16; 4],
+  /// Depth of discharge at last QMax update per cell
+  pub qmax_dod0: [u16; 4],
+  /// Passed capacity since last QMax update in mAh
+  pub qmax_passed_q: u16,
+  /// Time passed since last QMax update in hours/16
+  pub qmax_time: u16,
+  /// Thermal model parameters
+  pub temp_k: i16,
+  pub temp_a: i16,
+  /// Raw depth of discharge at last OCV reading per cell
+  pub raw_dod0: [u16; 4],
+}
+
+impl GaugeStatus3 {
+  pub(super) fn from_block(data: &[u8]) -> Self {
+    GaugeStatus3 {
+      qmax: [0, 1, 2, 3].map(|i| word(data, i)),
+      qmax_dod0: [4, 5, 6, 7].map(|i| word(data, i)),
+      qmax_passed_q: word(data, 8),
+      qmax_time: word(data, 9),
+      temp_k: word(data, 10) as i16,
+      temp_a: word(data, 11) as i16,
+      raw_dod0: [12, 13, 14, 15].map(|i| word(data, i)),
+    }
+  }
+
+  /// Difference between largest and smallest learned cell QMax in mAh
+  pub fn qmax_imbalance(&self) -> u16 {
+    let max = self.qmax.iter().max().copied().unwrap_or(0);
+    let min = self.qmax.iter().min().copied().unwrap_or(0);
+    max - min
+  }
+}
+
+/// StateOfHealth() full charge capacity and energy
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateOfHealth {
+  /// State-of-health full charge capacity in mAh
+  pub fcc: u16,
+  /// State-of-health full charge energy in cWh
+  pub fce: u16,
+}
+
+impl StateOfHealth {
+  pub(super) fn from_block(data: &[u8]) -> Self {
+    StateOfHealth {
+      fcc: word(data, 0),
+      fce: word(data, 1),
+    }
+  }
+}
+
+/// FilteredCapacity() values, reported even if [SMOOTH] = 0
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FilteredCapacity {
+  /// Remaining capacity in mAh
+  pub remaining_capacity: u16,
+  /// Remaining energy in cWh
+  pub remaining_energy: u16,
+  /// Full charge capacity in mAh
+  pub full_charge_capacity: u16,
+  /// Full charge energy in cWh
+  pub full_charge_energy: u16,
+}
+
+impl FilteredCapacity {
+  pub(super) fn from_block(data: &[u8]) -> Self {
+    FilteredCapacity {
+      remaining_capacity: word(data, 0),
+      remaining_energy: word(data, 1),
+      full_charge_capacity: word(data, 2),
+      full_charge_energy: word(data, 3),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::vec::Vec;
+
+  use super::*;
+  use crate::bq4050::{Address, CmdBlock, Error, BQ4050};
+  use crate::mock::{MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+
+  fn block_read(cmd: CmdBlock, data: &[u8]) -> [Transaction; 2] {
+    let mut reply = Vec::from([data.len() as u8]);
+    reply.extend_from_slice(data);
+    [
+      Transaction::write(DEV, &[cmd as u8]),
+      Transaction::read(DEV, &reply),
+    ]
+  }
+
+  fn words(values: &[u16]) -> Vec<u8> {
+    values
+      .iter()
+      .flat_map(|value| value.to_le_bytes())
+      .collect()
+  }
+
+  #[test]
+  fn gauge_status_decode() {
+    let mut status2 = Vec::from([0x03, 0x06, 0x01, 0x02, 0x03, 0x04]);
+    status2.extend_from_slice(&86_400u32.to_le_bytes());
+    status2.extend(words(&[
+      4700, 4710, 4720, 4730, 150, 55, 32, 1200, 1210, 1220, 1230,
+    ]));
+
+    let script = [
+      block_read(
+        CmdBlock::GaugeStatus1Reg,
+        &words(&[
+          2800, 1036, 2900, 1073, 3100, 1147, 2981, 2971, 1000, 1010, 1020, 1030, 95, 96, 97, 98,
+        ]),
+      ),
+      block_read(CmdBlock::GaugeStatus2Reg, &status2),
+      block_read(
+        CmdBlock::GaugeStatus3Reg,
+        &words(&[
+          3200, 3180, 3210, 3190, 1100, 1110, 1120, 1130, 2600, 48, 0xFF38, 250, 4600, 4610, 4620,
+          4630,
+        ]),
+      ),
+      block_read(CmdBlock::StateOfHealthReg, &words(&[3050, 1129])),
+      block_read(
+        CmdBlock::FilteredCapacityReg,
+        &words(&[2750, 1018, 3080, 1140]),
+      ),
+    ]
+    .concat();
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script));
+
+    assert_eq!(
+      bq4050.get_gauge_status1().unwrap(),
+      GaugeStatus1 {
+        true_rem_q: 2800,
+        true_rem_e: 1036,
+        initial_q: 2900,
+        initial_e: 1073,
+        true_full_chg_q: 3100,
+        true_full_chg_e: 1147,
+        t_sim: DeciKelvin(2981),
+        t_ambient: DeciKelvin(2971),
+        ra_scale: [1000, 1010, 1020, 1030],
+        comp_res: [95, 96, 97, 98],
+      }
+    );
+    assert_eq!(
+      bq4050.get_gauge_status2().unwrap(),
+      GaugeStatus2 {
+        pack_grid: 3,
+        lstatus: 6,
+        cell_grid: [1, 2, 3, 4],
+        state_time: 86_400,
+        dod0: [4700, 4710, 4720, 4730],
+        dod0_passed_q: 150,
+        dod0_passed_e: 55,
+        dod0_time: 32,
+        dodeoc: [1200, 1210, 1220, 1230],
+      }
+    );
+    let status3 = bq4050.get_gauge_status3().unwrap();
+    assert_eq!(
+      status3,
+      GaugeStatus3 {
+        qmax: [3200, 3180, 3210, 3190],
+        qmax_dod0: [1100, 1110, 1120, 1130],
+        qmax_passed_q: 2600,
+        qmax_time: 48,
+        temp_k: -200,
+        temp_a: 250,
+        raw_dod0: [4600, 4610, 4620, 4630],
+      }
+    );
+    assert_eq!(status3.qmax_imbalance(), 30);
+    assert_eq!(
+      bq4050.get_state_of_health().unwrap(),
+      StateOfHealth {
+        fcc: 3050,
+        fce: 1129
+      }
+    );
+    assert_eq!(
+      bq4050.get_filtered_capacity().unwrap(),
+      FilteredCapacity {
+        remaining_capacity: 2750,
+        remaining_energy: 1018,
+        full_charge_capacity: 3080,
+        full_charge_energy: 1140,
+      }
+    );
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn short_block_is_rejected() {
+    let script = [
+      block_read(CmdBlock::GaugeStatus1Reg, &[0u8; 30]),
+      block_read(CmdBlock::StateOfHealthReg, &words(&[3050])),
+    ]
+    .concat();
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script));
+
+    assert!(matches!(
+      bq4050.get_gauge_status1(),
+      Err(Error::InvalidLength(30))
+    ));
+    assert!(matches!(
+      bq4050.get_state_of_health(),
+      Err(Error::InvalidLength(2))
+    ));
+    bq4050.release().done();
+  }
+}
@@ -0,0 +1,164 @@
+use core::fmt;
+
+use heapless::String;
+
+use super::{Capacity, MilliVolts, BLOCK_MAX};
+
+// 13.27 0x1A SpecificationInfo() fields
+const SPEC_REVISION_SHIFT: u16 = 0;
+const SPEC_VERSION_SHIFT: u16 = 4;
+const SPEC_VSCALE_SHIFT: u16 = 8;
+const SPEC_IPSCALE_SHIFT: u16 = 12;
+const SPEC_FIELD_MASK: u16 = 0b1111;
+
+/// SpecificationInfo() of the Smart Battery Data specification the gauge follows
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SpecificationInfo {
+  /// 0x01 - Version 1.0 and 1.1
+  pub revision: u8,
+  /// 0x01 - Version 1.0, 0x02 - Version 1.1, 0x03 - Version 1.1 with PEC
+  pub version: u8,
+  /// Voltage scaling exponent, voltages are multiplied by 10^vscale
+  pub vscale: u8,
+  /// Current and capacity scaling exponent, values are multiplied by 10^ipscale
+  pub ipscale: u8,
+}
+
+impl SpecificationInfo {
+  /// Gauge supports SMBus packet error checking
+  pub fn supports_pec(&self) -> bool {
+    self.version == 0x03
+  }
+}
+
+impl From<u16> for SpecificationInfo {
+  fn from(raw: u16) -> Self {
+    let field = |shift: u16| ((raw >> shift) & SPEC_FIELD_MASK) as u8;
+
+    SpecificationInfo {
+      revision: field(SPEC_REVISION_SHIFT),
+      version: field(SPEC_VERSION_SHIFT),
+      vscale: field(SPEC_VSCALE_SHIFT),
+      ipscale: field(SPEC_IPSCALE_SHIFT),
+    }
+  }
+}
+
+/// ManufacturerDate() decoded from Day + Month*32 + (Year–1980)*512
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ManufacturerDate {
+  pub year: u16,
+  pub month: u8,
+  pub day: u8,
+}
+
+impl From<u16> for ManufacturerDate {
+  fn from(raw: u16) -> Self {
+    ManufacturerDate {
+      year: 1980 + (raw >> 9),
+      month: ((raw >> 5) & 0b1111) as u8,
+      day: (raw & 0b1_1111) as u8,
+    }
+  }
+}
+
+impl From<ManufacturerDate> for u16 {
+  fn from(date: ManufacturerDate) -> Self {
+    (date.year.saturating_sub(1980) << 9) | ((date.month as u16) << 5) | date.day as u16
+  }
+}
+
+impl fmt::Display for ManufacturerDate {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+  }
+}
+
+/// Everything that identifies the installed pack
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackIdentity {
+  pub serial_number: u16,
+  pub manufacture_date: ManufacturerDate,
+  pub manufacturer_name: String<BLOCK_MAX>,
+  pub device_name: String<BLOCK_MAX>,
+  pub device_chemistry: String<BLOCK_MAX>,
+  pub design_capacity: Capacity,
+  pub design_voltage: MilliVolts,
+  pub specification_info: SpecificationInfo,
+}
+
+#[cfg(test)]
+mod tests {
+  use std::string::ToString;
+
+  use super::*;
+  use crate::bq4050::{Address, CmdBlock, Error, BQ4050};
+  use crate::mock::{MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+
+  #[test]
+  fn manufacturer_date_decode() {
+    // Day + Month*32 + (Year-1980)*512
+    let cases = [
+      (0x0000, 1980, 0, 0, "1980-00-00"),
+      (0x0021, 1980, 1, 1, "1980-01-01"),
+      (0x56B1, 2023, 5, 17, "2023-05-17"),
+      (0x599F, 2024, 12, 31, "2024-12-31"),
+      (0xFFFF, 2107, 15, 31, "2107-15-31"),
+    ];
+
+    for (raw, year, month, day, text) in cases {
+      let date = ManufacturerDate::from(raw);
+      assert_eq!(date, ManufacturerDate { year, month, day }, "{:04x}", raw);
+      assert_eq!(date.to_string(), text);
+      assert_eq!(u16::from(date), raw);
+    }
+  }
+
+  #[test]
+  fn specification_info_decode() {
+    // (raw, revision, version, vscale, ipscale, PEC)
+    let cases = [
+      (0x0011, 1, 1, 0, 0, false),
+      (0x0021, 1, 2, 0, 0, false),
+      (0x0031, 1, 3, 0, 0, true),
+      (0x2131, 1, 3, 1, 2, true),
+    ];
+
+    for (raw, revision, version, vscale, ipscale, pec) in cases {
+      let info = SpecificationInfo::from(raw);
+      assert_eq!(
+        info,
+        SpecificationInfo {
+          revision,
+          version,
+          vscale,
+          ipscale
+        },
+        "{:04x}",
+        raw
+      );
+      assert_eq!(info.supports_pec(), pec, "{:04x}", raw);
+    }
+  }
+
+  #[test]
+  fn manufacturer_name_read() {
+    let cmd = CmdBlock::ManufacturerNameReg as u8;
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[
+      Transaction::write(DEV, &[cmd]),
+      Transaction::read(DEV, b"\x0cTexas Inst.\0"),
+      // Length byte past the 32 byte block
+      Transaction::write(DEV, &[cmd]),
+      Transaction::read(DEV, &[33]),
+    ]));
+
+    assert_eq!(bq4050.manufacturer_name().unwrap(), "Texas Inst.");
+    assert!(matches!(
+      bq4050.manufacturer_name(),
+      Err(Error::InvalidLength(33))
+    ));
+    bq4050.release().done();
+  }
+}
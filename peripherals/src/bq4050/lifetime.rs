@@ -0,0 +1,338 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::{MilliAmps, MilliVolts};
+
+// 13.1.x LifetimeDataBlock1() - LifetimeDataBlock5() layout.
+// Minimal length of every block, replies can be padded by the gauge.
+pub(super) const LIFETIME_BLOCK_LEN: [usize; 5] = [32, 8, 16, 32, 32];
+
+/// Number of safety events and the cycle count when the last one happened
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventCount {
+  pub count: u16,
+  pub last_cycle: u16,
+}
+
+/// Time spent in each of the charging temperature ranges, in hours
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TimeInTemperature {
+  pub under: u16,
+  pub low: u16,
+  pub standard_low: u16,
+  pub recommended: u16,
+  pub standard_high: u16,
+  pub high: u16,
+  pub over: u16,
+}
+
+/// Safety events recorded over the pack life
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SafetyEvents {
+  pub cov: EventCount,
+  pub cuv: EventCount,
+  pub ocd1: EventCount,
+  pub ocd2: EventCount,
+  pub occ1: EventCount,
+  pub occ2: EventCount,
+  pub aold: EventCount,
+  pub ascd: EventCount,
+  pub ascc: EventCount,
+  pub otc: EventCount,
+  pub otd: EventCount,
+  pub otf: EventCount,
+  pub valid_charge_termination: EventCount,
+  pub qmax_updates: EventCount,
+  pub ra_updates: EventCount,
+  pub ra_disable: EventCount,
+}
+
+/// Lifetime data collected by the gauge. Temperatures are in °C
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LifetimeData {
+  // Block 1
+  pub max_cell_voltage: [MilliVolts; 4],
+  pub min_cell_voltage: [MilliVolts; 4],
+  pub max_delta_cell_voltage: MilliVolts,
+  pub max_charge_current: MilliAmps,
+  pub max_discharge_current: MilliAmps,
+  pub max_avg_discharge_current: MilliAmps,
+  /// cW
+  pub max_avg_discharge_power: i16,
+  pub max_cell_temperature: i8,
+  pub min_cell_temperature: i8,
+  pub max_delta_cell_temperature: i8,
+  pub max_internal_temperature: i8,
+  pub min_internal_temperature: i8,
+  pub max_fet_temperature: i8,
+  // Block 2
+  pub shutdowns: u8,
+  pub partial_resets: u8,
+  pub full_resets: u8,
+  pub wdt_resets: u8,
+  /// Cell balancing time per cell, in units of 2 hours
+  pub cb_time: [u8; 4],
+  // Block 3
+  /// Total firmware runtime, in hours
+  pub total_runtime: u16,
+  pub time_in_temperature: TimeInTemperature,
+  // Blocks 4 and 5
+  pub events: SafetyEvents,
+}
+
+impl LifetimeData {
+  // Decodes block `index` (0 for LifetimeDataBlock1) into the matching fields
+  pub(super) fn parse_block(&mut self, index: usize, data: &[u8]) {
+    match index {
+      0 => self.parse_block1(data),
+      1 => self.parse_block2(data),
+      2 => self.parse_block3(data),
+      3 => self.parse_block4(data),
+      _ => self.parse_block5(data),
+    }
+  }
+
+  fn parse_block1(&mut self, data: &[u8]) {
+    let word = |i: usize| LittleEndian::read_u16(&data[i * 2..i * 2 + 2]);
+
+    for cell in 0..4 {
+      self.max_cell_voltage[cell] = MilliVolts(word(cell));
+      self.min_cell_voltage[cell] = MilliVolts(word(4 + cell));
+    }
+    self.max_delta_cell_voltage = MilliVolts(word(8));
+    self.max_charge_current = MilliAmps(word(9) as i16);
+    self.max_discharge_current = MilliAmps(word(10) as i16);
+    self.max_avg_discharge_current = MilliAmps(word(11) as i16);
+    self.max_avg_discharge_power = word(12) as i16;
+    self.max_cell_temperature = data[26] as i8;
+    self.min_cell_temperature = data[27] as i8;
+    self.max_delta_cell_temperature = data[28] as i8;
+    self.max_internal_temperature = data[29] as i8;
+    self.min_internal_temperature = data[30] as i8;
+    self.max_fet_temperature = data[31] as i8;
+  }
+
+  fn parse_block2(&mut self, data: &[u8]) {
+    self.shutdowns = data[0];
+    self.partial_resets = data[1];
+    self.full_resets = data[2];
+    self.wdt_resets = data[3];
+    self.cb_time.copy_from_slice(&data[4..8]);
+  }
+
+  fn parse_block3(&mut self, data: &[u8]) {
+    let word = |i: usize| LittleEndian::read_u16(&data[i * 2..i * 2 + 2]);
+
+    self.total_runtime = word(0);
+    self.time_in_temperature = TimeInTemperature {
+      under: word(1),
+      low: word(2),
+      standard_low: word(3),
+      recommended: word(4),
+      standard_high: word(5),
+      high: word(6),
+      over: word(7),
+    };
+  }
+
+  fn parse_block4(&mut self, data: &[u8]) {
+    let event = |i: usize| event_count(&data[i * 4..i * 4 + 4]);
+
+    self.events.cov = event(0);
+    self.events.cuv = event(1);
+    self.events.ocd1 = event(2);
+    self.events.ocd2 = event(3);
+    self.events.occ1 = event(4);
+    self.events.occ2 = event(5);
+    self.events.aold = event(6);
+    self.events.ascd = event(7);
+  }
+
+  fn parse_block5(&mut self, data: &[u8]) {
+    let event = |i: usize| event_count(&data[i * 4..i * 4 + 4]);
+
+    self.events.ascc = event(0);
+    self.events.otc = event(1);
+    self.events.otd = event(2);
+    self.events.otf = event(3);
+    self.events.valid_charge_termination = event(4);
+    self.events.qmax_updates = event(5);
+    self.events.ra_updates = event(6);
+    self.events.ra_disable = event(7);
+  }
+}
+
+fn event_count(data: &[u8]) -> EventCount {
+  EventCount {
+    count: LittleEndian::read_u16(&data[0..2]),
+    last_cycle: LittleEndian::read_u16(&data[2..4]),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::vec::Vec;
+
+  use super::*;
+  use crate::bq4050::{Address, CmdBlock, Error, BQ4050};
+  use crate::mock::{MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+
+  fn block_read(cmd: CmdBlock, data: &[u8]) -> [Transaction; 2] {
+    let mut reply = Vec::from([data.len() as u8]);
+    reply.extend_from_slice(data);
+    [
+      Transaction::write(DEV, &[cmd as u8]),
+      Transaction::read(DEV, &reply),
+    ]
+  }
+
+  fn words(values: &[i16]) -> Vec<u8> {
+    values
+      .iter()
+      .flat_map(|value| value.to_le_bytes())
+      .collect()
+  }
+
+  // Event i happened i + 1 times, last time at cycle 10 * i
+  fn events() -> Vec<u8> {
+    words(&[1, 0, 2, 10, 3, 20, 4, 30, 5, 40, 6, 50, 7, 60, 8, 70])
+  }
+
+  fn event(i: u16) -> EventCount {
+    EventCount {
+      count: i + 1,
+      last_cycle: 10 * i,
+    }
+  }
+
+  fn blocks() -> [Vec<u8>; 5] {
+    let mut block1 = words(&[
+      4200, 4190, 4180, 4170, 3000, 3010, 3020, 3030, 35, 3000, -5000, -2500, -1800,
+    ]);
+    block1.extend_from_slice(&[45, 0xFB, 12, 50, 10, 60]);
+
+    [
+      block1,
+      Vec::from([1, 2, 3, 4, 5, 6, 7, 8]),
+      words(&[1234, 1, 2, 3, 4, 5, 6, 7]),
+      events(),
+      events(),
+    ]
+  }
+
+  const COMMANDS: [CmdBlock; 5] = [
+    CmdBlock::LifetimeDataBlock1Reg,
+    CmdBlock::LifetimeDataBlock2Reg,
+    CmdBlock::LifetimeDataBlock3Reg,
+    CmdBlock::LifetimeDataBlock4Reg,
+    CmdBlock::LifetimeDataBlock5Reg,
+  ];
+
+  #[test]
+  fn lifetime_data_decode() {
+    let script: Vec<_> = COMMANDS
+      .into_iter()
+      .zip(blocks())
+      .flat_map(|(cmd, data)| block_read(cmd, &data))
+      .collect();
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script));
+
+    let data = bq4050.get_lifetime_data().unwrap();
+    assert_eq!(
+      data.max_cell_voltage,
+      [4200, 4190, 4180, 4170].map(MilliVolts)
+    );
+    assert_eq!(
+      data.min_cell_voltage,
+      [3000, 3010, 3020, 3030].map(MilliVolts)
+    );
+    assert_eq!(data.max_delta_cell_voltage, MilliVolts(35));
+    assert_eq!(data.max_charge_current, MilliAmps(3000));
+    assert_eq!(data.max_discharge_current, MilliAmps(-5000));
+    assert_eq!(data.max_avg_discharge_current, MilliAmps(-2500));
+    assert_eq!(data.max_avg_discharge_power, -1800);
+    assert_eq!(
+      [
+        data.max_cell_temperature,
+        data.min_cell_temperature,
+        data.max_delta_cell_temperature,
+        data.max_internal_temperature,
+        data.min_internal_temperature,
+        data.max_fet_temperature,
+      ],
+      [45, -5, 12, 50, 10, 60]
+    );
+
+    assert_eq!(
+      [
+        data.shutdowns,
+        data.partial_resets,
+        data.full_resets,
+        data.wdt_resets
+      ],
+      [1, 2, 3, 4]
+    );
+    assert_eq!(data.cb_time, [5, 6, 7, 8]);
+
+    assert_eq!(data.total_runtime, 1234);
+    assert_eq!(
+      data.time_in_temperature,
+      TimeInTemperature {
+        under: 1,
+        low: 2,
+        standard_low: 3,
+        recommended: 4,
+        standard_high: 5,
+        high: 6,
+        over: 7,
+      }
+    );
+
+    let events = data.events;
+    assert_eq!(
+      [
+        events.cov,
+        events.cuv,
+        events.ocd1,
+        events.ocd2,
+        events.occ1,
+        events.occ2,
+        events.aold,
+        events.ascd
+      ],
+      [0, 1, 2, 3, 4, 5, 6, 7].map(event)
+    );
+    assert_eq!(
+      [
+        events.ascc,
+        events.otc,
+        events.otd,
+        events.otf,
+        events.valid_charge_termination,
+        events.qmax_updates,
+        events.ra_updates,
+        events.ra_disable
+      ],
+      [0, 1, 2, 3, 4, 5, 6, 7].map(event)
+    );
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn short_block_is_rejected() {
+    let [block1, ..] = blocks();
+    let script = [
+      block_read(COMMANDS[0], &block1),
+      block_read(COMMANDS[1], &[1, 2, 3, 4]),
+    ]
+    .concat();
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script));
+
+    assert!(matches!(
+      bq4050.get_lifetime_data(),
+      Err(Error::InvalidLength(4))
+    ));
+    bq4050.release().done();
+  }
+}
@@ -0,0 +1,189 @@
+// 13.1 ManufacturerAccess() subcommands
+// Issued through 0x44 ManufacturerBlockAccess(), see `BQ4050::mac_read` and `BQ4050::mac_write`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MacCmd {
+  DeviceType = 0x0001,
+  FirmwareVersion = 0x0002,
+  HardwareVersion = 0x0003,
+  IfChecksum = 0x0004,
+  StaticDfSignature = 0x0005,
+  ChemId = 0x0006,
+  StaticChemDfSignature = 0x0008,
+  AllDfSignature = 0x0009,
+  ShutdownMode = 0x0010,
+  SleepMode = 0x0011,
+  AutoCcOffset = 0x0013,
+  FuseToggle = 0x001D,
+  PrechargeFet = 0x001E,
+  ChgFet = 0x001F,
+  DsgFet = 0x0020,
+  Gauging = 0x0021,
+  FetControl = 0x0022,
+  LifetimeDataCollection = 0x0023,
+  PermanentFailure = 0x0024,
+  BlackBoxRecorder = 0x0025,
+  Fuse = 0x0026,
+  LedDisplayEnable = 0x0027,
+  LifetimeDataReset = 0x0028,
+  PermanentFailDataReset = 0x0029,
+  BlackBoxRecorderReset = 0x002A,
+  LedToggle = 0x002B,
+  LedDisplayPress = 0x002C,
+  CalibrationMode = 0x002D,
+  LifetimeDataFlush = 0x002E,
+  LifetimeDataSpeedUpMode = 0x002F,
+  SealDevice = 0x0030,
+  SecurityKeys = 0x0035,
+  AuthenticationKey = 0x0037,
+  DeviceReset = 0x0041,
+  SafetyAlert = 0x0050,
+  SafetyStatus = 0x0051,
+  PfAlert = 0x0052,
+  PfStatus = 0x0053,
+  OperationStatus = 0x0054,
+  ChargingStatus = 0x0055,
+  GaugingStatus = 0x0056,
+  ManufacturingStatus = 0x0057,
+  AfeRegister = 0x0058,
+  LifetimeDataBlock1 = 0x0060,
+  LifetimeDataBlock2 = 0x0061,
+  LifetimeDataBlock3 = 0x0062,
+  LifetimeDataBlock4 = 0x0063,
+  LifetimeDataBlock5 = 0x0064,
+  ManufacturerInfo = 0x0070,
+  DaStatus1 = 0x0071,
+  DaStatus2 = 0x0072,
+  GaugeStatus1 = 0x0073,
+  GaugeStatus2 = 0x0074,
+  GaugeStatus3 = 0x0075,
+  CbStatus = 0x0076,
+  StateOfHealth = 0x0077,
+  FilteredCapacity = 0x0078,
+  RomMode = 0x0F00,
+  ExitCalibrationOutput = 0xF080,
+  OutputCcAndAdcForCalibration = 0xF081,
+  OutputShortedCcAndAdcForCalibration = 0xF082,
+}
+
+impl From<MacCmd> for u16 {
+  fn from(cmd: MacCmd) -> Self {
+    cmd as u16
+  }
+}
+
+/// Maximum payload of a single ManufacturerBlockAccess() transfer, not counting the subcommand echo
+pub const MAC_BLOCK_MAX: usize = 32;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bq4050::{Address, Error, BQ4050};
+  use crate::mock::{MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+  const MAC: u8 = Address::Mac as u8;
+
+  // Subcommand write followed by the block read of the reply
+  fn mac_script(subcommand: u16, reply: &[u8]) -> [Transaction; 3] {
+    let [low, high] = subcommand.to_le_bytes();
+    [
+      Transaction::write(DEV, &[MAC, 2, low, high]),
+      Transaction::write(DEV, &[MAC]),
+      Transaction::read(DEV, reply),
+    ]
+  }
+
+  #[test]
+  fn mac_read_reply() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&mac_script(
+      0x0001,
+      &[4, 0x01, 0x00, 0x50, 0x40],
+    )));
+
+    let mut buf = [0u8; 2];
+    assert_eq!(bq4050.mac_read(MacCmd::DeviceType, &mut buf).unwrap(), 2);
+    assert_eq!(buf, [0x50, 0x40]);
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn mac_read_drops_what_does_not_fit() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&mac_script(
+      0x0054,
+      &[6, 0x54, 0x00, 1, 2, 3, 4],
+    )));
+
+    let mut buf = [0u8; 2];
+    assert_eq!(
+      bq4050.mac_read(MacCmd::OperationStatus, &mut buf).unwrap(),
+      2
+    );
+    assert_eq!(buf, [1, 2]);
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn mac_read_wrong_echo() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&mac_script(
+      0x0001,
+      &[4, 0x02, 0x00, 0x50, 0x40],
+    )));
+
+    let mut buf = [0u8; 2];
+    assert!(matches!(
+      bq4050.mac_read(MacCmd::DeviceType, &mut buf),
+      Err(Error::UnexpectedSubcommand(0x0002))
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn mac_read_length_too_short() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&mac_script(0x0001, &[1, 0x01])));
+
+    let mut buf = [0u8; 2];
+    assert!(matches!(
+      bq4050.mac_read(MacCmd::DeviceType, &mut buf),
+      Err(Error::InvalidLength(1))
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn mac_read_length_too_long() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&mac_script(0x0001, &[0xFF, 0x01, 0x00])));
+
+    let mut buf = [0u8; 2];
+    assert!(matches!(
+      bq4050.mac_read(MacCmd::DeviceType, &mut buf),
+      Err(Error::InvalidLength(0xFF))
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn mac_read_over_block_max() {
+    // Subcommand echo and one byte more than MAC_BLOCK_MAX of data
+    let len = MAC_BLOCK_MAX as u8 + 3;
+    let mut bq4050 = BQ4050::new(MockI2c::new(&mac_script(0x0060, &[len, 0x60, 0x00])));
+
+    let mut buf = [0u8; MAC_BLOCK_MAX];
+    assert!(matches!(
+      bq4050.mac_read(MacCmd::LifetimeDataBlock1, &mut buf),
+      Err(Error::InvalidLength(l)) if l == len
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn mac_write_over_block_max() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[]));
+
+    let data = [0u8; MAC_BLOCK_MAX + 1];
+    assert!(matches!(
+      bq4050.mac_write(MacCmd::AuthenticationKey, &data),
+      Err(Error::InvalidLength(l)) if l as usize == data.len()
+    ));
+    bq4050.release().done();
+  }
+}
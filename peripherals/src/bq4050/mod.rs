@@ -0,0 +1,1071 @@
+use byteorder::{ByteOrder, LittleEndian};
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+use heapless::{String, Vec};
+
+pub mod flash;
+
+#[cfg(feature = "async")]
+mod asynch;
+mod auth;
+mod balancing;
+mod calibration;
+mod control;
+mod dastatus;
+mod gauge;
+mod info;
+mod lifetime;
+mod mac;
+mod operation;
+mod regs;
+mod retry;
+mod safety;
+mod security;
+mod smbus;
+mod snapshot;
+mod status;
+mod units;
+mod version;
+
+#[cfg(feature = "async")]
+pub use asynch::BQ4050Async;
+pub use auth::{auth_response, Sha1, AUTH_CHALLENGE_LEN, AUTH_KEY_LEN, AUTH_RESPONSE_LEN};
+pub use balancing::CbStatus;
+pub use calibration::{CalibrationAverage, CalibrationData, TemperatureSensor};
+pub use dastatus::{DaStatus1, DaStatus2};
+pub use flash::{DfParam, DfType, DfValue};
+pub use gauge::{FilteredCapacity, GaugeStatus1, GaugeStatus2, GaugeStatus3, StateOfHealth};
+pub use info::{ManufacturerDate, PackIdentity, SpecificationInfo};
+pub use lifetime::{EventCount, LifetimeData, SafetyEvents, TimeInTemperature};
+pub use mac::{MacCmd, MAC_BLOCK_MAX};
+pub use operation::{
+  ChargingStatus, GaugingStatus, ManufacturingStatus, OperationStatus, SecurityMode,
+  TemperatureRange,
+};
+pub use retry::{BusErrorKind, RetryPolicy};
+pub use safety::{PfFlag, PfFlags, SafetyFlag, SafetyFlags};
+pub use snapshot::{BatterySnapshot, Reading, SnapshotField, SnapshotFields, SnapshotPoller};
+pub use status::{BatteryMode, BatteryStatus, ErrorCode};
+pub use units::{
+  Capacity, CapacityUnit, CentiOhms, CentiWatts, DeciKelvin, MilliAmps, MilliVolts, Minutes,
+  Percent, Rate,
+};
+pub use version::{FirmwareVersion, GaugeVersion};
+
+use balancing::CB_STATUS_LEN;
+use dastatus::{DA_STATUS1_LEN, DA_STATUS2_LEN};
+use gauge::{
+  FILTERED_CAPACITY_LEN, GAUGE_STATUS1_LEN, GAUGE_STATUS2_LEN, GAUGE_STATUS3_LEN,
+  STATE_OF_HEALTH_LEN,
+};
+use lifetime::LIFETIME_BLOCK_LEN;
+
+/// I2C address
+#[derive(Copy, Clone)]
+pub enum Address {
+  /// Device address
+  Dev = 0x0B,
+  /// Register of MAC
+  Mac = 0x44,
+}
+
+// Each methods refers the corresponding section and command from bq4050 Technical Reference https://www.ti.com/lit/ug/sluuaq3a/sluuaq3a.pdf
+// Read word commands
+#[derive(Copy, Clone)]
+pub enum Cmd {
+  RemainingCapacityAlarmReg = 0x01,
+  RemainingTimeAlarmReg = 0x02,
+  BatteryModeReg = 0x03,
+  AtRateReg = 0x04,
+  AtRateTimeToFullReg = 0x05,
+  AtRateTimeToEmptyReg = 0x06,
+  AtRateOkReg = 0x07,
+  TemperatureReg = 0x08,
+  VoltageReg = 0x09,
+  CurrentReg = 0x0A,
+  AverageCurrentReg = 0x0B,
+  MaxErrorReg = 0x0C,
+  RelativeSocReg = 0x0D,
+  AbsoluteSocReg = 0x0E,
+  RemainingCapacityReg = 0x0F,
+  FullChargeCapacityReg = 0x10,
+  RunTimeToEmptyReg = 0x11,
+  AverageTimeToEmptyReg = 0x12,
+  AverageTimeToFullReg = 0x13,
+  ChargingCurrentReg = 0x14,
+  ChargingVoltageReg = 0x15,
+  BatteryStatusReg = 0x16,
+  CycleCountReg = 0x17,
+  DesignCapacityReg = 0x18,
+  DesignVoltageReg = 0x19,
+  SpecificationInfoReg = 0x1A,
+  ManufacturerDateReg = 0x1B,
+  CellVoltage4Reg = 0x3C,
+  CellVoltage3Reg = 0x3D,
+  CellVoltage2Reg = 0x3E,
+  CellVoltage1Reg = 0x3F,
+  BtpDischargeSetReg = 0x4A,
+  BtpChargeSetReg = 0x4B,
+  SohReg = 0x4F,
+  TurboPowerReg = 0x59,
+  TurboFinalReg = 0x5A,
+  TurboPackRReg = 0x5B,
+  TurboSysRReg = 0x5C,
+  TurboEdvReg = 0x5D,
+  TurboCurrentReg = 0x5E,
+  NoLoadRemCapReg = 0x5F,
+  SerialNumberReg = 0x1C,
+}
+
+// Read block commands
+#[derive(Copy, Clone)]
+pub enum CmdBlock {
+  ManufacturerNameReg = 0x20,
+  DEVICENAMEReg = 0x21,
+  DeviceChemistryReg = 0x22,
+  ManufacturerDataReg = 0x23,
+  AuthenticateReg = 0x2F,
+  SafetyAlertReg = 0x50,
+  SafetyStatusReg = 0x51,
+  PfAlertReg = 0x52,
+  PfStatusReg = 0x53,
+  LifetimeDataBlock1Reg = 0x60,
+  LifetimeDataBlock2Reg = 0x61,
+  LifetimeDataBlock3Reg = 0x62,
+  LifetimeDataBlock4Reg = 0x63,
+  LifetimeDataBlock5Reg = 0x64,
+  DaStatus1Reg = 0x71,
+  DaStatus2Reg = 0x72,
+  GaugeStatus1Reg = 0x73,
+  GaugeStatus2Reg = 0x74,
+  GaugeStatus3Reg = 0x75,
+  CbStatusReg = 0x76,
+  StateOfHealthReg = 0x77,
+  FilteredCapacityReg = 0x78,
+}
+
+/// Maximum payload of SMBus block transfer
+pub const BLOCK_MAX: usize = 32;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Error<I2cError> {
+  I2cError(I2cError),
+  /// Gauge did not acknowledge, usually busy or the command is not available
+  Nack(I2cError),
+  /// Bus transaction timed out
+  Timeout(I2cError),
+  /// Length byte of the block reply is out of range
+  InvalidLength(u8),
+  /// ManufacturerBlockAccess() reply echoed another subcommand
+  UnexpectedSubcommand(u16),
+  /// ASCII block contains non-ASCII data
+  InvalidString,
+  /// PEC byte of the reply does not match the received data
+  PecMismatch,
+  /// Written capacity is in another unit than BatteryMode()[CAPM] selects
+  CapacityUnitMismatch,
+  /// Security key was not accepted, holds the security mode the gauge stayed in
+  KeyRejected(SecurityMode),
+  /// Gauge did not enter SEALED mode, holds the security mode the gauge stayed in
+  SealFailed(SecurityMode),
+  /// Address is outside of data flash
+  InvalidFlashAddress(u16),
+  /// Value does not match the type or range of data flash parameter
+  InvalidValue,
+  /// Command requires UNSEALED or FULL ACCESS mode
+  Sealed,
+  /// FET toggle was refused, see `BQ4050::allow_fet_control`
+  FetControlNotAllowed,
+}
+
+pub struct BQ4050<I2C: WriteRead> {
+  i2c: I2C,
+  // Sorts errors of the HAL, see `BQ4050::set_error_classifier`
+  classify: fn(&I2C::Error) -> BusErrorKind,
+  // Applied to read transactions, see `BQ4050::set_retry_policy`
+  retry: RetryPolicy,
+  pec: bool,
+  // Cached BatteryMode()[CAPM], read on the first capacity access
+  capacity_unit: Option<CapacityUnit>,
+  // Guards manual FET toggles
+  fet_control_allowed: bool,
+  repeated_start: bool,
+}
+
+impl<I2C, I2cError> BQ4050<I2C>
+where
+  I2C: WriteRead<Error = I2cError> + Write<Error = I2cError> + Read<Error = I2cError>,
+{
+  pub fn new(i2c: I2C) -> BQ4050<I2C> {
+    BQ4050 {
+      i2c,
+      classify: retry::unclassified,
+      retry: RetryPolicy::NONE,
+      pec: false,
+      capacity_unit: None,
+      fet_control_allowed: false,
+      repeated_start: false,
+    }
+  }
+
+  pub fn release(self) -> I2C {
+    self.i2c
+  }
+
+  /// Enables SMBus packet error checking.
+  /// When enabled, every word and block transaction carries CRC-8 which is verified on reads.
+  /// Reads then always use repeated start as the CRC covers the command write too.
+  pub fn set_pec(&mut self, enabled: bool) {
+    self.pec = enabled;
+  }
+
+  pub fn pec_enabled(&self) -> bool {
+    self.pec
+  }
+
+  /// Enables PEC if SpecificationInfo() reports support for it. Returns the new PEC state.
+  pub fn detect_pec(&mut self) -> Result<bool, Error<I2cError>> {
+    self.pec = false;
+    self.pec = self.get_specification_info()?.supports_pec();
+    Ok(self.pec)
+  }
+
+  /// Reads with repeated start instead of separate write and read transactions.
+  /// Has no effect while PEC is enabled, PEC reads always use repeated start.
+  pub fn set_repeated_start(&mut self, enabled: bool) {
+    self.repeated_start = enabled;
+  }
+
+  pub fn repeated_start_enabled(&self) -> bool {
+    self.repeated_start
+  }
+
+  // 13.1 0x44 ManufacturerBlockAccess()
+  // This read/write block function issues MAC subcommands and returns their data.
+  // Subcommand is written as a block, then a block read returns the subcommand echo followed by the data.
+  // Protocol - Block
+  // Returns amount of data bytes copied into `buf`. Data that does not fit into `buf` is dropped.
+  pub fn mac_read(
+    &mut self,
+    subcommand: impl Into<u16>,
+    buf: &mut [u8],
+  ) -> Result<usize, Error<I2cError>> {
+    let subcommand = subcommand.into();
+    // Subcommand write is repeated too, it only selects what is read
+    self.retry_read(|bq| {
+      bq.mac_write(subcommand, &[])?;
+
+      let mut block = [0u8; MAC_BLOCK_MAX + 2];
+      let len = bq.read_block_once(Address::Mac as u8, &mut block)?;
+      smbus::decode_mac(subcommand, &block[..len], buf)
+    })
+  }
+
+  pub fn mac_write(
+    &mut self,
+    subcommand: impl Into<u16>,
+    data: &[u8],
+  ) -> Result<(), Error<I2cError>> {
+    let mut buffer = [0u8; MAC_BLOCK_MAX + 2];
+    let len = smbus::encode_mac(subcommand.into(), data, &mut buffer)?;
+    self.write_block_raw(Address::Mac as u8, &buffer[..len])
+  }
+
+  // Reads 2 to 4 bytes long MAC flags reply as u32
+  fn mac_read_flags(&mut self, cmd: MacCmd) -> Result<u32, Error<I2cError>> {
+    let mut buffer = [0u8; 4];
+    let len = self.mac_read(cmd, &mut buffer)?;
+    smbus::decode_flags(&buffer, len)
+  }
+
+  // 13.2 0x01 RemainingCapacityAlarm()
+  // This read/write word function sets a low capacity alarm threshold for the cell stack.
+  // Protocol - Word
+  // Unit - If BatteryMode()[CAPM]= 0, then the data reports in mAh.
+  // Unit - If BatteryMode()[CAPM]= 1, then the data reports in 10 mWh.
+  pub fn get_remaining_capacity_alarm(&mut self) -> Result<Capacity, Error<I2cError>> {
+    self.read_capacity(Cmd::RemainingCapacityAlarmReg)
+  }
+
+  pub fn set_remaining_capacity_alarm(&mut self, value: Capacity) -> Result<(), Error<I2cError>> {
+    self.write_capacity(Cmd::RemainingCapacityAlarmReg, value)
+  }
+
+  // 13.3 0x02 RemainingTimeAlarm()
+  // This read/write word function sets a low remaining time-to-fully discharge alarm threshold for the cell stack.
+  // Protocol - Word
+  // Unit - min
+  pub fn get_remaining_time_alarm(&mut self) -> Result<Minutes, Error<I2cError>> {
+    Ok(Minutes(self.read_word(Cmd::RemainingTimeAlarmReg as u8)?))
+  }
+
+  pub fn set_remaining_time_alarm(&mut self, value: Minutes) -> Result<(), Error<I2cError>> {
+    self.write_word(Cmd::RemainingTimeAlarmReg as u8, value.0)
+  }
+
+  // 13.4 0x03 BatteryMode()
+  // This read/write word function sets various battery operating mode options.
+  // Protocol - Word
+  // See `BatteryMode` for the flags description
+  pub fn get_battery_mode(&mut self) -> Result<BatteryMode, Error<I2cError>> {
+    self.read_word_reg(&regs::BATTERY_MODE)
+  }
+
+  pub fn set_battery_mode(&mut self, mode: BatteryMode) -> Result<(), Error<I2cError>> {
+    self.write_word(Cmd::BatteryModeReg as u8, mode.into())?;
+    self.capacity_unit = Some(capacity_unit(&mode));
+    Ok(())
+  }
+
+  // Unit of the capacity registers, BatteryMode()[CAPM] is read once and cached afterwards.
+  // CAPM is reset by the gauge on reset, call `invalidate_capacity_unit` after resetting the device.
+  pub fn get_capacity_unit(&mut self) -> Result<CapacityUnit, Error<I2cError>> {
+    match self.capacity_unit {
+      Some(unit) => Ok(unit),
+      None => {
+        let unit = capacity_unit(&self.get_battery_mode()?);
+        self.capacity_unit = Some(unit);
+        Ok(unit)
+      }
+    }
+  }
+
+  pub fn set_capacity_unit(&mut self, unit: CapacityUnit) -> Result<(), Error<I2cError>> {
+    let mut mode = self.get_battery_mode()?;
+    mode.capm = unit == CapacityUnit::TenMilliWattHours;
+    self.set_battery_mode(mode)
+  }
+
+  pub fn invalidate_capacity_unit(&mut self) {
+    self.capacity_unit = None;
+  }
+
+  fn read_capacity(&mut self, cmd: Cmd) -> Result<Capacity, Error<I2cError>> {
+    let unit = self.get_capacity_unit()?;
+    Ok(Capacity::new(unit, self.read_word(cmd as u8)?))
+  }
+
+  fn write_capacity(&mut self, cmd: Cmd, value: Capacity) -> Result<(), Error<I2cError>> {
+    if value.unit() != self.get_capacity_unit()? {
+      return Err(Error::CapacityUnitMismatch);
+    }
+
+    self.write_word(cmd as u8, value.raw())
+  }
+
+  // 13.5 0x04 AtRate()
+  // This read/write word function sets the value used in calculating AtRateTimeToFull() and AtRateTimeToEmpty().
+  // Protocol - Word
+  // Format - SignedInt. Positive for charge, negative for discharge
+  // Unit - If BatteryMode()[CAPM]= 0, then the data reports in mA.
+  // Unit - If BatteryMode()[CAPM]= 1, then the data reports in 10 mW.
+  pub fn get_at_rate(&mut self) -> Result<Rate, Error<I2cError>> {
+    let unit = self.get_capacity_unit()?;
+    Ok(Rate::new(
+      unit,
+      self.read_word(Cmd::AtRateReg as u8)? as i16,
+    ))
+  }
+
+  pub fn set_at_rate(&mut self, value: Rate) -> Result<(), Error<I2cError>> {
+    if value.unit() != self.get_capacity_unit()? {
+      return Err(Error::CapacityUnitMismatch);
+    }
+
+    self.write_word(Cmd::AtRateReg as u8, value.raw() as u16)
+  }
+
+  // 13.6 0x05 AtRateTimeToFull()
+  // This word read function returns the remaining time-to-fully charge the battery stack.
+  // Protocol - Word
+  // Unit - min
+  pub fn get_at_rate_time_to_full(&mut self) -> Result<Option<Minutes>, Error<I2cError>> {
+    Ok(minutes(self.read_word(Cmd::AtRateTimeToFullReg as u8)?))
+  }
+
+  // 13.7 0x06 AtRateTimeToEmpty()
+  // This word read function returns the remaining time-to-fully discharge the battery stack.
+  // Protocol - Word
+  // Unit - min
+  pub fn get_at_rate_time_to_empty(&mut self) -> Result<Option<Minutes>, Error<I2cError>> {
+    Ok(minutes(self.read_word(Cmd::AtRateTimeToEmptyReg as u8)?))
+  }
+
+  // 13.8 0x07 AtRateOK()
+  // This read-word function returns a Boolean value that indicates whether the battery can deliver AtRate() for at least 10s.
+  // Protocol - Word
+  pub fn get_at_rate_ok(&mut self) -> Result<bool, Error<I2cError>> {
+    Ok(self.read_word(Cmd::AtRateOkReg as u8)? != 0)
+  }
+
+  // 13.9 0x08 Temperature()
+  // This read-word function returns the temperature in units 0.1°K.
+  // Protocol - Word
+  // Unit - 0.1°K
+  pub fn get_temperature(&mut self) -> Result<DeciKelvin, Error<I2cError>> {
+    self.read_word_reg(&regs::TEMPERATURE)
+  }
+
+  /// Reads SMBus block into `buf` honoring the length byte. Returns the length reported by the device.
+  /// Fails with `Error::InvalidLength` if the reply does not fit into `buf`.
+  pub fn read_block(&mut self, cmd: CmdBlock, buf: &mut [u8]) -> Result<usize, Error<I2cError>> {
+    self.read_block_raw(cmd as u8, buf)
+  }
+
+  // Reads block of exactly N bytes
+  fn read_block_array<const N: usize>(&mut self, cmd: u8) -> Result<[u8; N], Error<I2cError>> {
+    let mut buffer = [0u8; N];
+    let len = self.read_block_raw(cmd, &mut buffer)?;
+    if len != N {
+      return Err(Error::InvalidLength(len as u8));
+    }
+
+    Ok(buffer)
+  }
+
+  // Reads block of at least `min` bytes, the rest of the buffer is zeroed
+  fn read_block_at_least(
+    &mut self,
+    cmd: CmdBlock,
+    min: usize,
+  ) -> Result<[u8; BLOCK_MAX], Error<I2cError>> {
+    let mut buffer = [0u8; BLOCK_MAX];
+    let len = self.read_block(cmd, &mut buffer)?;
+    if len < min {
+      return Err(Error::InvalidLength(len as u8));
+    }
+
+    Ok(buffer)
+  }
+
+  fn read_block_u32(&mut self, cmd: CmdBlock) -> Result<u32, Error<I2cError>> {
+    Ok(LittleEndian::read_u32(
+      &self.read_block_array::<4>(cmd as u8)?,
+    ))
+  }
+
+  fn read_block_string(&mut self, cmd: CmdBlock) -> Result<String<BLOCK_MAX>, Error<I2cError>> {
+    let mut buffer = [0u8; BLOCK_MAX];
+    let len = self.read_block(cmd, &mut buffer)?;
+
+    // Names are usually padded with NULs up to the block length
+    let text = &buffer[..len];
+    let text = match text.iter().position(|&c| c == 0) {
+      Some(end) => &text[..end],
+      None => text,
+    };
+
+    if !text.is_ascii() {
+      return Err(Error::InvalidString);
+    }
+
+    let mut res = String::new();
+    // Always fits as `text` is not longer than BLOCK_MAX
+    let _ = res.push_str(core::str::from_utf8(text).map_err(|_| Error::InvalidString)?);
+    Ok(res)
+  }
+
+  // 13.10 0x09 Voltage()
+  // This read-word function returns the sum of the measured cell voltages.
+  // Protocol - Word
+  // Unit - mV
+  pub fn get_voltage(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    self.read_word_reg(&regs::VOLTAGE)
+  }
+  // 13.11 0x0A Current()
+  // This read-word function returns the measured current from the coulomb counter.
+  // If the input to the device exceeds the maximum value,the value is clamped at the maximum and does not roll over.
+  // Protocol - Word
+  // Format - SignedInt
+  // Unit - mA
+  pub fn get_current(&mut self) -> Result<MilliAmps, Error<I2cError>> {
+    self.read_word_reg(&regs::CURRENT)
+  }
+  // 13.12 0x0B AverageCurrent()
+  // Protocol - Word
+  // Format - SignedInt
+  // Unit - mA
+  pub fn get_average_current(&mut self) -> Result<MilliAmps, Error<I2cError>> {
+    self.read_word_reg(&regs::AVERAGE_CURRENT)
+  }
+  // 13.13 0x0C MaxError()
+  // This read-word function returns the expected margin of error, in %, in the state-of-charge calculation with a rangeof 1 to 100%.
+  // Protocol - Word
+  // Unit - %
+  pub fn get_max_error(&mut self) -> Result<Percent, Error<I2cError>> {
+    Ok(Percent(self.read_word(Cmd::MaxErrorReg as u8)?))
+  }
+
+  // 13.14 0x0D RelativeStateOfCharge()
+  // This read-word function returns the predicted remaining battery capacity as a percentage of FullChargeCapacity().
+  // Protocol - Word
+  // Unit - %
+  pub fn get_relative_state_of_charge(&mut self) -> Result<Percent, Error<I2cError>> {
+    self.read_word_reg(&regs::RELATIVE_SOC)
+  }
+
+  // 13.15 0x0E AbsoluteStateOfCharge()
+  // This read-word function returns the predicted remaining battery capacity as a percentage.
+  // Protocol - Word
+  // Unit - %
+  pub fn get_absolute_state_of_charge(&mut self) -> Result<Percent, Error<I2cError>> {
+    self.read_word_reg(&regs::ABSOLUTE_SOC)
+  }
+
+  // 13.16 0x0F RemainingCapacity()
+  // This read-word function returns the predicted remaining battery capacity.
+  // Protocol - Word
+  // Unit - If BatteryMode()[CAPM]= 0, then the data reports in mAh.
+  // Unit - If BatteryMode()[CAPM]= 1, then the data reports in 10 mWh.
+  pub fn get_remaining_capacity(&mut self) -> Result<Capacity, Error<I2cError>> {
+    self.read_capacity(Cmd::RemainingCapacityReg)
+  }
+
+  // 13.17 0x10 FullChargeCapacity()
+  // This read-word function returns the predicted battery capacity when fully charged.
+  // The value returned will not be updated during charging.
+  // Protocol - Word
+  // Unit - If BatteryMode()[CAPM]= 0, then the data reports in mAh.
+  // Unit - If BatteryMode()[CAPM]= 1, then the data reports in 10 mWh.
+  pub fn get_full_charge_capacity(&mut self) -> Result<Capacity, Error<I2cError>> {
+    self.read_capacity(Cmd::FullChargeCapacityReg)
+  }
+
+  // 13.18 0x11 RunTimeToEmpty()
+  // This read-word function returns the predicted remaining battery capacity based on the present rate of discharge.
+  // Protocol - Word
+  // Unit - min
+  // 65535 = Battery is not being discharged.
+  pub fn get_run_time_to_empty(&mut self) -> Result<Option<Minutes>, Error<I2cError>> {
+    self.read_word_reg(&regs::RUN_TIME_TO_EMPTY)
+  }
+
+  // 13.19 0x12 AverageTimeToEmpty()
+  // This read-word function returns the predicted remaining battery capacity based on AverageCurrent().
+  // Protocol - Word
+  // Unit - min
+  // 65535 = Battery is not being discharged.
+  pub fn get_average_time_to_empty(&mut self) -> Result<Option<Minutes>, Error<I2cError>> {
+    self.read_word_reg(&regs::AVERAGE_TIME_TO_EMPTY)
+  }
+
+  // 13.20 0x13 AverageTimeToFull()
+  // This read-word function returns the predicted time-to-full charge based on AverageCurrent().
+  // Protocol - Word
+  // Unit - min
+  // 65535 = Battery is not being charged.
+  pub fn get_average_time_to_full(&mut self) -> Result<Option<Minutes>, Error<I2cError>> {
+    self.read_word_reg(&regs::AVERAGE_TIME_TO_FULL)
+  }
+
+  // 13.21 0x14 ChargingCurrent()
+  // This read-word function returns the desired charging current.
+  // Protocol - Word
+  // Unit - mA, unsigned
+  pub fn get_charging_current(&mut self) -> Result<MilliAmps, Error<I2cError>> {
+    Ok(MilliAmps(
+      self.read_word(Cmd::ChargingCurrentReg as u8)? as i16
+    ))
+  }
+
+  // 13.22 0x15 ChargingVoltage()
+  // This read-word function returns the desired charging voltage.
+  // Protocol - Word
+  // Unit - mV
+  pub fn get_charging_voltage(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    Ok(MilliVolts(self.read_word(Cmd::ChargingVoltageReg as u8)?))
+  }
+
+  // 13.23 0x16 BatteryStatus()
+  // This read-word function returns various battery status information.
+  // Protocol - Word
+  // See `BatteryStatus` for the flags description
+  pub fn get_battery_status(&mut self) -> Result<BatteryStatus, Error<I2cError>> {
+    self.read_word_reg(&regs::BATTERY_STATUS)
+  }
+
+  // 13.24 0x17 CycleCount()
+  // This read-word function returns the number of discharge cycles the battery has experienced.
+  // The default value is stored in the data flash value CycleCount, which is updated in runtime.
+  // Protocol - Word
+  // Unit - cycles
+  pub fn get_cycle_count(&mut self) -> Result<u16, Error<I2cError>> {
+    self.read_word_reg(&regs::CYCLE_COUNT)
+  }
+
+  // 13.25 0x18 DesignCapacity()
+  // This read-word function returns the theoretical pack capacity.
+  // The default value is stored in the data flash value Design Capacity mAh or Design Capacityc Wh.
+  // Protocol - Word
+  // Unit - If BatteryMode()[CAPM]= 0, then the data reports in mAh.
+  // Unit - If BatteryMode()[CAPM]= 1, then the data reports in 10 mWh.
+  pub fn get_design_capacity(&mut self) -> Result<Capacity, Error<I2cError>> {
+    self.read_capacity(Cmd::DesignCapacityReg)
+  }
+
+  // 13.26 0x19 DesignVoltage()
+  // This read-word function returns the theoretical pack voltage.
+  // The default value is stored in data flash value Design Voltage.
+  // Protocol - Word
+  // Unit - mV
+  pub fn get_design_voltage(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    Ok(MilliVolts(self.read_word(Cmd::DesignVoltageReg as u8)?))
+  }
+
+  // 13.27 0x1A SpecificationInfo()
+  // This read-word function returns the version and scaling of the Smart Battery Data specification.
+  // Protocol - Word
+  // See `SpecificationInfo` for the fields description
+  pub fn get_specification_info(&mut self) -> Result<SpecificationInfo, Error<I2cError>> {
+    Ok(SpecificationInfo::from(
+      self.read_word(Cmd::SpecificationInfoReg as u8)?,
+    ))
+  }
+
+  // 13.28 0x1B ManufacturerDate()
+  // This read-word function returns the pack's manufacturer date.
+  // Protocol - Word
+  // ManufacturerDate() value in the following format: Day + Month*32+ (Year–1980)*512
+  pub fn get_manufacturer_date(&mut self) -> Result<ManufacturerDate, Error<I2cError>> {
+    Ok(ManufacturerDate::from(
+      self.read_word(Cmd::ManufacturerDateReg as u8)?,
+    ))
+  }
+
+  // 13.29 0x1C SerialNumber()
+  // This read-word function returns the assigned pack serial number.
+  // Protocol - Word
+  pub fn get_serial_number(&mut self) -> Result<u16, Error<I2cError>> {
+    self.read_word(Cmd::SerialNumberReg as u8)
+  }
+
+  // Collects identification data of the pack: 0x18, 0x19, 0x1A, 0x1B, 0x1C, 0x20, 0x21 and 0x22
+  pub fn get_pack_identity(&mut self) -> Result<PackIdentity, Error<I2cError>> {
+    Ok(PackIdentity {
+      serial_number: self.get_serial_number()?,
+      manufacture_date: self.get_manufacturer_date()?,
+      manufacturer_name: self.manufacturer_name()?,
+      device_name: self.device_name()?,
+      device_chemistry: self.device_chemistry()?,
+      design_capacity: self.get_design_capacity()?,
+      design_voltage: self.get_design_voltage()?,
+      specification_info: self.get_specification_info()?,
+    })
+  }
+
+  // 13.30 0x20 ManufacturerName()
+  // This read-block function returns the pack manufacturer's name.
+  // Protocol - Block
+  // Unit - ASCII
+  pub fn manufacturer_name(&mut self) -> Result<String<BLOCK_MAX>, Error<I2cError>> {
+    self.read_block_string(CmdBlock::ManufacturerNameReg)
+  }
+
+  // 13.31 0x21 DeviceName()
+  // This read-block function returns the assigned pack name.
+  // Protocol - Block
+  // Unit - ASCII
+  pub fn device_name(&mut self) -> Result<String<BLOCK_MAX>, Error<I2cError>> {
+    self.read_block_string(CmdBlock::DEVICENAMEReg)
+  }
+
+  // 13.32 0x22 DeviceChemistry()
+  // This read-block function returns the battery chemistry used in the pack.
+  // Protocol - Block
+  // Unit - ASCII
+  pub fn device_chemistry(&mut self) -> Result<String<BLOCK_MAX>, Error<I2cError>> {
+    self.read_block_string(CmdBlock::DeviceChemistryReg)
+  }
+
+  // 13.33 0x23 ManufacturerData()
+  // This read-block function returns ManufacturerInfo by default.
+  // The command also returns a response to MAC command in order to maintain compatibility of the MAC system in bq30zxy family.
+  // Protocol - Block
+  pub fn manufacturer_data(&mut self) -> Result<Vec<u8, BLOCK_MAX>, Error<I2cError>> {
+    let mut buffer = [0u8; BLOCK_MAX];
+    let len = self.read_block(CmdBlock::ManufacturerDataReg, &mut buffer)?;
+
+    // Always fits as `len` is not longer than BLOCK_MAX
+    Ok(Vec::from_slice(&buffer[..len]).unwrap_or_default())
+  }
+
+  // 13.34 0x2F Authenticate()
+  // This read/write block function provides SHA-1 authentication to send the challenge and read the response in the default mode.
+  // It is also used to input a new authentication key when the MAC AuthenticationKey() is used.
+  // Protocol - Block
+  /// Writes SHA-1 challenge, or the new key after AuthenticationKey()
+  pub fn send_challenge(&mut self, challenge: &[u8]) -> Result<(), Error<I2cError>> {
+    self.write_block_raw(CmdBlock::AuthenticateReg as u8, challenge)
+  }
+
+  /// Reads SHA-1 response, available 250 ms after the challenge
+  pub fn read_auth_response(&mut self) -> Result<[u8; AUTH_RESPONSE_LEN], Error<I2cError>> {
+    self.read_block_array::<AUTH_RESPONSE_LEN>(CmdBlock::AuthenticateReg as u8)
+  }
+
+  // 13.35 0x3C CellVoltage4()
+  // This read-word function returns the Cell 4 voltage.
+  // Protocol - Word
+  // Unit - mV
+  pub fn get_cell_voltage_4(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    self.read_word_reg(&regs::CELL_VOLTAGE_4)
+  }
+  // 13.36 0x3D CellVoltage3()
+  // This read-word function returns the Cell 3 voltage.
+  // Protocol - Word
+  // Unit - mV
+  pub fn get_cell_voltage_3(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    self.read_word_reg(&regs::CELL_VOLTAGE_3)
+  }
+  // 13.37 0x3E CellVoltage2()
+  // This read-word function returns the Cell 2 voltage.
+  // Protocol - Word
+  // Unit - mV
+  pub fn get_cell_voltage_2(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    self.read_word_reg(&regs::CELL_VOLTAGE_2)
+  }
+  // 13.38 0x3F CellVoltage1()
+  // This read-word function returns the Cell 1 voltage.
+  // Protocol - Word
+  // Unit - mV
+  pub fn get_cell_voltage_1(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    self.read_word_reg(&regs::CELL_VOLTAGE_1)
+  }
+  // 13.39 0x4A BTPDischargeSet()
+  // This read/write word command updates the BTP set threshold for discharge mode for the next BTP interrupt,
+  // de-asserts the present BTP interrupt, and clears the OperationStatus()[BTP_INT] bit.
+  // Unit - If BatteryMode()[CAPM]= 0, then the data reports in mAh.
+  // Unit - If BatteryMode()[CAPM]= 1, then the data reports in 10 mWh.
+  pub fn get_btp_discharge_set(&mut self) -> Result<Capacity, Error<I2cError>> {
+    self.read_capacity(Cmd::BtpDischargeSetReg)
+  }
+
+  pub fn set_btp_discharge_set(&mut self, value: Capacity) -> Result<(), Error<I2cError>> {
+    self.write_capacity(Cmd::BtpDischargeSetReg, value)
+  }
+
+  // 13.40 0x4B BTPChargeSet()
+  // This read/write word command updates the BTP set threshold for charge mode for the next BTP interrupt,
+  // de-asserts the present BTP interrupt, and clears the OperationStatus()[BTP_INT] bit.
+  // Unit - If BatteryMode()[CAPM]= 0, then the data reports in mAh.
+  // Unit - If BatteryMode()[CAPM]= 1, then the data reports in 10 mWh.
+  pub fn get_btp_charge_set(&mut self) -> Result<Capacity, Error<I2cError>> {
+    self.read_capacity(Cmd::BtpChargeSetReg)
+  }
+
+  pub fn set_btp_charge_set(&mut self, value: Capacity) -> Result<(), Error<I2cError>> {
+    self.write_capacity(Cmd::BtpChargeSetReg, value)
+  }
+
+  // 13.41 0x4F State-of-Health(SoH)
+  // This read word command returns the SoH information of the battery in percentage of design capacity and design energy.
+  pub fn get_soh(&mut self) -> Result<Percent, Error<I2cError>> {
+    Ok(Percent(self.read_word(Cmd::SohReg as u8)?))
+  }
+
+  // 13.42 0x50 SafetyAlert
+  // This command returns the SafetyAlert() flags. For a description of each bit flag, see the ManufacturerAccess()
+  // version of the same command in Section 13.1.
+  // Protocol - Block
+  // NOTE: This command and commands 0x51 to 0x58 are not accessible in SEALED mode.
+  pub fn get_safety_alert(&mut self) -> Result<SafetyFlags, Error<I2cError>> {
+    self.read_flags_reg(&regs::SAFETY_ALERT)
+  }
+
+  // 13.43 0x51 SafetyStatus
+  // This command returns the SafetyStatus() flags. For a description of each bit flag, see the ManufacturerAccess()
+  // version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_safety_status(&mut self) -> Result<SafetyFlags, Error<I2cError>> {
+    self.read_flags_reg(&regs::SAFETY_STATUS)
+  }
+
+  // 13.44 0x52 PFAlert
+  // This command returns the PFAlert() flags. For a description of each bit flag, see the ManufacturerAccess()
+  // version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_pf_alert(&mut self) -> Result<PfFlags, Error<I2cError>> {
+    self.read_flags_reg(&regs::PF_ALERT)
+  }
+
+  // 13.45 0x53 PFStatus
+  // This command returns the PFStatus() flags. For a description of each bit flag, see the ManufacturerAccess()
+  // version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_pf_status(&mut self) -> Result<PfFlags, Error<I2cError>> {
+    self.read_flags_reg(&regs::PF_STATUS)
+  }
+
+  // 13.46 0x54 OperationStatus
+  // This command returns the OperationStatus() flags. For a description of each bit flag, see the ManufacturerAccess()
+  // version of the same command in Section 13.1.
+  // Protocol - Block
+  // Read through ManufacturerBlockAccess() as 0x54 is not accessible in SEALED mode
+  pub fn get_operation_status(&mut self) -> Result<OperationStatus, Error<I2cError>> {
+    self.read_mac_flags_reg(&regs::OPERATION_STATUS)
+  }
+
+  pub fn security_mode(&mut self) -> Result<SecurityMode, Error<I2cError>> {
+    Ok(self.get_operation_status()?.security_mode)
+  }
+
+  // 13.47 0x55 ChargingStatus
+  // This command returns the ChargingStatus() flags. For a description of each bit flag, see the ManufacturerAccess()
+  // version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_charging_status(&mut self) -> Result<ChargingStatus, Error<I2cError>> {
+    self.read_mac_flags_reg(&regs::CHARGING_STATUS)
+  }
+
+  // 13.48 0x56 GaugingStatus
+  // This command returns the GaugingStatus() flags. For a description of each bit flag, see the ManufacturerAccess()
+  // version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_gauging_status(&mut self) -> Result<GaugingStatus, Error<I2cError>> {
+    self.read_mac_flags_reg(&regs::GAUGING_STATUS)
+  }
+
+  // 13.49 0x57 ManufacturingStatus
+  // This command returns the ManufacturingStatus() flags. For a description of each bit flag, see the ManufacturerAccess()
+  // version of the same command in Section 13.1.
+  // Protocol - Block
+  // Read through ManufacturerBlockAccess()
+  pub fn get_manufacturing_status(&mut self) -> Result<ManufacturingStatus, Error<I2cError>> {
+    Ok(ManufacturingStatus::from(
+      self.mac_read_flags(MacCmd::ManufacturingStatus)?,
+    ))
+  }
+
+  // 13.50 0x58 AFE Register
+  // This command returns a snapshotof the AFE register settings. For a description of each bit flag, see the ManufacturerAccess()
+  // version of the same command in Section 13.1.
+  // Protocol - Block
+  // ---
+
+  // 13.51 0x59 TURBO_POWER
+  // TURBO_POWER reports the maximal peak power value, MAX_POWER. The gauge computes a new RAM value every second.
+  // TURBO_POWER() is initialized to the result of the max power calculation at reset or power up.
+  // Protocol - Word
+  // Unit - cW
+  pub fn get_turbo_power(&mut self) -> Result<CentiWatts, Error<I2cError>> {
+    Ok(CentiWatts(self.read_word(Cmd::TurboPowerReg as u8)?))
+  }
+
+  // 13.52 0x5A TURBO_FINAL
+  // TURBO_FINAL sets Min Turbo Power, which represents the minimal TURBO BOOST mode power level during active operation (for example, non-SLEEP).
+  // Protocol - Word
+  // Unit - cW
+  pub fn get_turbo_final(&mut self) -> Result<CentiWatts, Error<I2cError>> {
+    Ok(CentiWatts(self.read_word(Cmd::TurboFinalReg as u8)?))
+  }
+
+  pub fn set_turbo_final(&mut self, value: CentiWatts) -> Result<(), Error<I2cError>> {
+    self.write_word(Cmd::TurboFinalReg as u8, value.0)
+  }
+
+  // 13.53 0x5B TURBO_PACK_R
+  // TURBO_PACK_R sets the PACK Resistance value of the battery pack serial resistance,
+  // including resistance associated with FETs, traces, sense resistors, and so on TURBO_PACK_R() accesses to the data flash value Pack Resistance.
+  // Protocol - Word
+  // Unit - cΩ
+  pub fn get_turbo_pack_r(&mut self) -> Result<CentiOhms, Error<I2cError>> {
+    Ok(CentiOhms(self.read_word(Cmd::TurboPackRReg as u8)?))
+  }
+
+  pub fn set_turbo_pack_r(&mut self, value: CentiOhms) -> Result<(), Error<I2cError>> {
+    self.write_word(Cmd::TurboPackRReg as u8, value.0)
+  }
+
+  // 13.54 0x5C TURBO_SYS_R
+  // TURBO_SYS_R sets the System Resistance value of the system serial resistance along the path from battery to system power converter
+  // input that includes FETs, traces, sense resistors, and so on TURBO_SYS_R() accesses to the data flash value System Resistance.
+  // Protocol - Word
+  // Unit - cΩ
+  pub fn get_turbo_sys_r(&mut self) -> Result<CentiOhms, Error<I2cError>> {
+    Ok(CentiOhms(self.read_word(Cmd::TurboSysRReg as u8)?))
+  }
+
+  pub fn set_turbo_sys_r(&mut self, value: CentiOhms) -> Result<(), Error<I2cError>> {
+    self.write_word(Cmd::TurboSysRReg as u8, value.0)
+  }
+
+  // 13.55 0x5D TURBO_EDV
+  // TURBO_EDV sets the Minimal Voltage at the system power converter input at which the system will still operate.
+  // TURBO_EDV() is written to the data flash value TerminateVoltage.
+  // Intended use is to write it once on first use to adjust for possible changes in system design from the time the battery pack was designed.
+  // Protocol - Word
+  // Unit - mV
+  pub fn get_turbo_edv(&mut self) -> Result<MilliVolts, Error<I2cError>> {
+    Ok(MilliVolts(self.read_word(Cmd::TurboEdvReg as u8)?))
+  }
+
+  pub fn set_turbo_edv(&mut self, value: MilliVolts) -> Result<(), Error<I2cError>> {
+    self.write_word(Cmd::TurboEdvReg as u8, value.0)
+  }
+
+  // 13.56 0x5E TURBO_CURRENT
+  // The gauge computes a maximal discharge current supported by the cell design for a C-rate discharge pulse for 10 ms.
+  // This value is updated every 1s for the system to read.
+  // Protocol - Word
+  // Unit - mA
+  // NOTE:This computes a maximal discharge current supported by the cell design.
+  pub fn get_turbo_current(&mut self) -> Result<MilliAmps, Error<I2cError>> {
+    Ok(MilliAmps(self.read_word(Cmd::TurboCurrentReg as u8)? as i16))
+  }
+
+  // 13.57 0x5F NoLoadRemCap()
+  // This read-only word command returns the equivalen to fRemainingCapacity() under a no load condition.
+  // For a description of returned data values, see theManufacturerAccess() version of same command in Section 13.1.
+  // Protocol - UnsignedInt
+  // Unit - If BatteryMode()[CAPM]= 0, then the data reports in mAh.
+  // Unit - If BatteryMode()[CAPM]= 1, then the data reports in 10 mWh.
+  pub fn get_no_load_rem_cap(&mut self) -> Result<Capacity, Error<I2cError>> {
+    self.read_capacity(Cmd::NoLoadRemCapReg)
+  }
+
+  // 13.58 0x60 LifetimeDataBlock1
+  // This command returns the first block of LifetimeData.
+  // For a description of returned data values, see theManufacturerAccess() version of the same command in Section 13.1.
+  // NOTE: This command and commands 0x61 to 0x78 are not accessible in SEALED mode.
+  // Protocol - Block
+  // ---
+  // 13.59 0x61 LifetimeDataBlock2
+  // This command returns the second block of LifetimeData.
+  // For a description of returned data values, see the ManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  // ---
+  // 13.60 0x62 LifetimeDataBlock3
+  // This command returns the third block of LifetimeData.
+  // For a description of returned data values, see theManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  // ---
+  // 13.61 0x63 LifetimeDataBlock4
+  // This command returns the third block of LifetimeData.
+  // For a description of returned data values, see theManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  // ---
+  // 13.62 0x64 LifetimeDataBlock5
+  // This command returns the third block of LifetimeData.
+  // For a description of returned data values, see theManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  /// Reads and combines all five lifetime data blocks
+  pub fn get_lifetime_data(&mut self) -> Result<LifetimeData, Error<I2cError>> {
+    let mut data = LifetimeData::default();
+
+    let blocks = [
+      CmdBlock::LifetimeDataBlock1Reg,
+      CmdBlock::LifetimeDataBlock2Reg,
+      CmdBlock::LifetimeDataBlock3Reg,
+      CmdBlock::LifetimeDataBlock4Reg,
+      CmdBlock::LifetimeDataBlock5Reg,
+    ];
+    for (index, cmd) in blocks.into_iter().enumerate() {
+      let block = self.read_block_at_least(cmd, LIFETIME_BLOCK_LEN[index])?;
+      data.parse_block(index, &block);
+    }
+
+    Ok(data)
+  }
+
+  // 13.63 0x70 ManufacturerInfo
+  // This command return smanufacturer information.
+  // For a description of returned data values, see theManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  // ---
+  // 13.64 0x71 DAStatus1
+  // This command returns the Cell Voltages, PackVoltage, Bat Voltage, Cell Currents, Cell Powers, Power,and AveragePower.
+  // For a description of returned data values, see the ManufacturerAccess() versionoft he samecomm and in Section 13.1.
+  // Protocol - Block
+  pub fn get_da_status1(&mut self) -> Result<DaStatus1, Error<I2cError>> {
+    self.read_block_reg(&regs::DA_STATUS1)
+  }
+
+  // 13.65 0x72 DAStatus2
+  // This command returns the internal temp sensor, TS1, TS2, TS3, TS4, Cell Temp, and FET Temp.
+  // For a description of returned data values, see theManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_da_status2(&mut self) -> Result<DaStatus2, Error<I2cError>> {
+    self.read_block_reg(&regs::DA_STATUS2)
+  }
+
+  // 13.66 0x73 GaugeStatus1
+  // This commandinstructsthe deviceto return Impedance Track related gauging information.
+  // For a description of returned data values, see theManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_gauge_status1(&mut self) -> Result<GaugeStatus1, Error<I2cError>> {
+    let block = self.read_block_at_least(CmdBlock::GaugeStatus1Reg, GAUGE_STATUS1_LEN)?;
+    Ok(GaugeStatus1::from_block(&block))
+  }
+
+  // 13.67 0x74 GaugeStatus2
+  // This commandinstructsthe deviceto return Impedance Track related gauging information.
+  // For a description of returned data values, see theManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_gauge_status2(&mut self) -> Result<GaugeStatus2, Error<I2cError>> {
+    let block = self.read_block_at_least(CmdBlock::GaugeStatus2Reg, GAUGE_STATUS2_LEN)?;
+    Ok(GaugeStatus2::from_block(&block))
+  }
+
+  // 13.68 0x75 GaugeStatus3
+  // This commandinstructsthe deviceto return Impedance Track related gauging information.
+  // For a description of returned data values, see theManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_gauge_status3(&mut self) -> Result<GaugeStatus3, Error<I2cError>> {
+    let block = self.read_block_at_least(CmdBlock::GaugeStatus3Reg, GAUGE_STATUS3_LEN)?;
+    Ok(GaugeStatus3::from_block(&block))
+  }
+
+  // 13.69 0x76 CBStatus
+  // This commandinstructsthe deviceto returncell balancetime information.
+  // For a description of returned data values, see the ManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_cb_status(&mut self) -> Result<CbStatus, Error<I2cError>> {
+    let block = self.read_block_at_least(CmdBlock::CbStatusReg, CB_STATUS_LEN)?;
+    Ok(CbStatus::from_block(&block))
+  }
+
+  /// Reports which of the four cells are being bled right now
+  pub fn get_cell_balancing(&mut self) -> Result<[bool; 4], Error<I2cError>> {
+    let active = self.get_operation_status()?.cb;
+    Ok(self.get_cb_status()?.balancing(active))
+  }
+
+  // 13.70 0x77 State-of-Health
+  // This commandinstructsthe deviceto returnthe state-of-healthfull chargecapacityand energy.
+  // For a description of returned data values, see theManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_state_of_health(&mut self) -> Result<StateOfHealth, Error<I2cError>> {
+    let block = self.read_block_at_least(CmdBlock::StateOfHealthReg, STATE_OF_HEALTH_LEN)?;
+    Ok(StateOfHealth::from_block(&block))
+  }
+
+  // 13.71 0x78 FilteredCapacity
+  // This commandinstructsthe deviceto returnthe filteredcapacityand energyevenif[SMOOTH]= 0.
+  // For a description of returned data values, see theManufacturerAccess() version of the same command in Section 13.1.
+  // Protocol - Block
+  pub fn get_filtered_capacity(&mut self) -> Result<FilteredCapacity, Error<I2cError>> {
+    let block = self.read_block_at_least(CmdBlock::FilteredCapacityReg, FILTERED_CAPACITY_LEN)?;
+    Ok(FilteredCapacity::from_block(&block))
+  }
+}
+
+fn capacity_unit(mode: &BatteryMode) -> CapacityUnit {
+  if mode.capm {
+    CapacityUnit::TenMilliWattHours
+  } else {
+    CapacityUnit::MilliAmpHours
+  }
+}
+
+// Time registers report 65535 when the value is not applicable
+fn minutes(raw: u16) -> Option<Minutes> {
+  match raw {
+    u16::MAX => None,
+    minutes => Some(Minutes(minutes)),
+  }
+}
+
+impl<E> From<E> for Error<E> {
+  fn from(error: E) -> Self {
+    Error::I2cError(error)
+  }
+}
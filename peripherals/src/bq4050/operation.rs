@@ -0,0 +1,552 @@
+// 13.1.x OperationStatus() bits
+const OP_PRES: u32 = 1 << 0;
+const OP_DSG: u32 = 1 << 1;
+const OP_CHG: u32 = 1 << 2;
+const OP_PCHG: u32 = 1 << 3;
+const OP_FUSE: u32 = 1 << 5;
+const OP_BTP_INT: u32 = 1 << 7;
+const OP_SEC_SHIFT: u32 = 8;
+const OP_SEC_MASK: u32 = 0b11;
+const OP_SDV: u32 = 1 << 10;
+const OP_SS: u32 = 1 << 11;
+const OP_PF: u32 = 1 << 12;
+const OP_XDSG: u32 = 1 << 13;
+const OP_XCHG: u32 = 1 << 14;
+const OP_SLEEP: u32 = 1 << 15;
+const OP_SDM: u32 = 1 << 16;
+const OP_LED: u32 = 1 << 17;
+const OP_AUTH: u32 = 1 << 18;
+const OP_AUTOCALM: u32 = 1 << 19;
+const OP_CAL: u32 = 1 << 20;
+const OP_CAL_OFFSET: u32 = 1 << 21;
+const OP_XL: u32 = 1 << 22;
+const OP_SLEEPM: u32 = 1 << 23;
+const OP_INIT: u32 = 1 << 24;
+const OP_SMBLCAL: u32 = 1 << 25;
+const OP_SLPAD: u32 = 1 << 26;
+const OP_SLPCC: u32 = 1 << 27;
+const OP_CB: u32 = 1 << 28;
+const OP_EMSHUT: u32 = 1 << 29;
+
+// 13.1.x ChargingStatus() bits
+const CHG_UT: u32 = 1 << 0;
+const CHG_LT: u32 = 1 << 1;
+const CHG_STL: u32 = 1 << 2;
+const CHG_RT: u32 = 1 << 3;
+const CHG_STH: u32 = 1 << 4;
+const CHG_HT: u32 = 1 << 5;
+const CHG_OT: u32 = 1 << 6;
+const CHG_PV: u32 = 1 << 8;
+const CHG_LV: u32 = 1 << 9;
+const CHG_MV: u32 = 1 << 10;
+const CHG_HV: u32 = 1 << 11;
+const CHG_IN: u32 = 1 << 12;
+const CHG_SU: u32 = 1 << 13;
+const CHG_MCHG: u32 = 1 << 14;
+const CHG_VCT: u32 = 1 << 15;
+
+// 13.1.x GaugingStatus() bits
+const GAUGE_FD: u32 = 1 << 0;
+const GAUGE_FC: u32 = 1 << 1;
+const GAUGE_TD: u32 = 1 << 2;
+const GAUGE_TC: u32 = 1 << 3;
+const GAUGE_BAL_EN: u32 = 1 << 4;
+const GAUGE_EDV: u32 = 1 << 5;
+const GAUGE_DSG: u32 = 1 << 6;
+const GAUGE_CF: u32 = 1 << 7;
+const GAUGE_REST: u32 = 1 << 8;
+const GAUGE_R_DIS: u32 = 1 << 10;
+const GAUGE_VOK: u32 = 1 << 11;
+const GAUGE_QEN: u32 = 1 << 12;
+const GAUGE_SLPQMAX: u32 = 1 << 13;
+const GAUGE_NSFM: u32 = 1 << 15;
+const GAUGE_VDQ: u32 = 1 << 16;
+const GAUGE_QMAX: u32 = 1 << 17;
+const GAUGE_RX: u32 = 1 << 18;
+const GAUGE_LDMD: u32 = 1 << 19;
+const GAUGE_OCVFR: u32 = 1 << 20;
+
+// 13.1.x ManufacturingStatus() bits
+const MFG_PCHG_TEST: u32 = 1 << 0;
+const MFG_CHG_TEST: u32 = 1 << 1;
+const MFG_DSG_TEST: u32 = 1 << 2;
+const MFG_GAUGE_EN: u32 = 1 << 3;
+const MFG_FET_EN: u32 = 1 << 4;
+const MFG_LF_EN: u32 = 1 << 5;
+const MFG_PF_EN: u32 = 1 << 6;
+const MFG_BBR_EN: u32 = 1 << 7;
+const MFG_FUSE_EN: u32 = 1 << 8;
+const MFG_LED_EN: u32 = 1 << 9;
+const MFG_CAL_EN: u32 = 1 << 15;
+
+/// Security mode reported in OperationStatus()[SEC1, SEC0]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SecurityMode {
+  Reserved,
+  FullAccess,
+  Unsealed,
+  Sealed,
+}
+
+impl From<u8> for SecurityMode {
+  fn from(raw: u8) -> Self {
+    match raw & 0b11 {
+      1 => SecurityMode::FullAccess,
+      2 => SecurityMode::Unsealed,
+      3 => SecurityMode::Sealed,
+      _ => SecurityMode::Reserved,
+    }
+  }
+}
+
+/// OperationStatus() flags
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OperationStatus {
+  /// System present low
+  pub pres: bool,
+  /// DSG FET is on
+  pub dsg: bool,
+  /// CHG FET is on
+  pub chg: bool,
+  /// Precharge FET is on
+  pub pchg: bool,
+  /// Fuse is blown
+  pub fuse: bool,
+  /// Battery trip point interrupt
+  pub btp_int: bool,
+  pub security_mode: SecurityMode,
+  /// Shutdown triggered via low pack voltage
+  pub sdv: bool,
+  /// Safety status is active
+  pub ss: bool,
+  /// Permanent failure mode is active
+  pub pf: bool,
+  /// Discharging disabled
+  pub xdsg: bool,
+  /// Charging disabled
+  pub xchg: bool,
+  /// SLEEP mode conditions met
+  pub sleep: bool,
+  /// Shutdown triggered via command
+  pub sdm: bool,
+  /// LED display is on
+  pub led: bool,
+  /// Authentication in progress
+  pub auth: bool,
+  /// Auto CC offset calibration by MAC AutoCCOffset()
+  pub autocalm: bool,
+  /// Calibration output, raw ADC and CC data
+  pub cal: bool,
+  /// Calibration output, raw CC offset data
+  pub cal_offset: bool,
+  /// 400-kHz SMBus mode
+  pub xl: bool,
+  /// SLEEP mode triggered via command
+  pub sleepm: bool,
+  /// Initialization after full reset
+  pub init: bool,
+  /// Auto CC calibration when the bus is low
+  pub smblcal: bool,
+  /// ADC measurement in SLEEP mode
+  pub slpad: bool,
+  /// CC measurement in SLEEP mode
+  pub slpcc: bool,
+  /// Cell balancing is active
+  pub cb: bool,
+  /// Emergency shutdown
+  pub emshut: bool,
+}
+
+impl From<u32> for OperationStatus {
+  fn from(raw: u32) -> Self {
+    OperationStatus {
+      pres: raw & OP_PRES != 0,
+      dsg: raw & OP_DSG != 0,
+      chg: raw & OP_CHG != 0,
+      pchg: raw & OP_PCHG != 0,
+      fuse: raw & OP_FUSE != 0,
+      btp_int: raw & OP_BTP_INT != 0,
+      security_mode: SecurityMode::from(((raw >> OP_SEC_SHIFT) & OP_SEC_MASK) as u8),
+      sdv: raw & OP_SDV != 0,
+      ss: raw & OP_SS != 0,
+      pf: raw & OP_PF != 0,
+      xdsg: raw & OP_XDSG != 0,
+      xchg: raw & OP_XCHG != 0,
+      sleep: raw & OP_SLEEP != 0,
+      sdm: raw & OP_SDM != 0,
+      led: raw & OP_LED != 0,
+      auth: raw & OP_AUTH != 0,
+      autocalm: raw & OP_AUTOCALM != 0,
+      cal: raw & OP_CAL != 0,
+      cal_offset: raw & OP_CAL_OFFSET != 0,
+      xl: raw & OP_XL != 0,
+      sleepm: raw & OP_SLEEPM != 0,
+      init: raw & OP_INIT != 0,
+      smblcal: raw & OP_SMBLCAL != 0,
+      slpad: raw & OP_SLPAD != 0,
+      slpcc: raw & OP_SLPCC != 0,
+      cb: raw & OP_CB != 0,
+      emshut: raw & OP_EMSHUT != 0,
+    }
+  }
+}
+
+/// Temperature range the charging algorithm is in
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TemperatureRange {
+  /// Undertemperature
+  Under,
+  Low,
+  StandardLow,
+  Recommended,
+  StandardHigh,
+  High,
+  /// Overtemperature
+  Over,
+  Unknown,
+}
+
+/// ChargingStatus() flags
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChargingStatus {
+  pub temperature_range: TemperatureRange,
+  /// Precharge voltage region
+  pub pv: bool,
+  /// Low voltage region
+  pub lv: bool,
+  /// Mid voltage region
+  pub mv: bool,
+  /// High voltage region
+  pub hv: bool,
+  /// Charge inhibit
+  pub inhibit: bool,
+  /// Charge suspend
+  pub suspend: bool,
+  /// Maintenance charge
+  pub mchg: bool,
+  /// Charge termination
+  pub vct: bool,
+}
+
+impl From<u32> for ChargingStatus {
+  fn from(raw: u32) -> Self {
+    let temperature_range = if raw & CHG_UT != 0 {
+      TemperatureRange::Under
+    } else if raw & CHG_LT != 0 {
+      TemperatureRange::Low
+    } else if raw & CHG_STL != 0 {
+      TemperatureRange::StandardLow
+    } else if raw & CHG_RT != 0 {
+      TemperatureRange::Recommended
+    } else if raw & CHG_STH != 0 {
+      TemperatureRange::StandardHigh
+    } else if raw & CHG_HT != 0 {
+      TemperatureRange::High
+    } else if raw & CHG_OT != 0 {
+      TemperatureRange::Over
+    } else {
+      TemperatureRange::Unknown
+    };
+
+    ChargingStatus {
+      temperature_range,
+      pv: raw & CHG_PV != 0,
+      lv: raw & CHG_LV != 0,
+      mv: raw & CHG_MV != 0,
+      hv: raw & CHG_HV != 0,
+      inhibit: raw & CHG_IN != 0,
+      suspend: raw & CHG_SU != 0,
+      mchg: raw & CHG_MCHG != 0,
+      vct: raw & CHG_VCT != 0,
+    }
+  }
+}
+
+/// GaugingStatus() flags
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct GaugingStatus {
+  /// Fully discharged
+  pub fd: bool,
+  /// Fully charged
+  pub fc: bool,
+  /// Terminate discharge
+  pub td: bool,
+  /// Terminate charge
+  pub tc: bool,
+  /// Cell balancing possible
+  pub bal_en: bool,
+  /// End-of-discharge termination voltage
+  pub edv: bool,
+  /// Discharge or relax
+  pub dsg: bool,
+  /// Condition flag
+  pub cf: bool,
+  /// Rest
+  pub rest: bool,
+  /// Resistance updates disabled
+  pub r_dis: bool,
+  /// Voltages are OK for QMax update
+  pub vok: bool,
+  /// Impedance Track gauging, QMax updates are enabled
+  pub qen: bool,
+  /// QMax update in SLEEP mode
+  pub slpqmax: bool,
+  /// Negative scale factor mode
+  pub nsfm: bool,
+  /// Discharge qualified for learning
+  pub vdq: bool,
+  /// QMax updated
+  pub qmax: bool,
+  /// Resistance updated
+  pub rx: bool,
+  /// Load mode
+  pub ldmd: bool,
+  /// OCV in flat region
+  pub ocvfr: bool,
+}
+
+impl From<u32> for GaugingStatus {
+  fn from(raw: u32) -> Self {
+    GaugingStatus {
+      fd: raw & GAUGE_FD != 0,
+      fc: raw & GAUGE_FC != 0,
+      td: raw & GAUGE_TD != 0,
+      tc: raw & GAUGE_TC != 0,
+      bal_en: raw & GAUGE_BAL_EN != 0,
+      edv: raw & GAUGE_EDV != 0,
+      dsg: raw & GAUGE_DSG != 0,
+      cf: raw & GAUGE_CF != 0,
+      rest: raw & GAUGE_REST != 0,
+      r_dis: raw & GAUGE_R_DIS != 0,
+      vok: raw & GAUGE_VOK != 0,
+      qen: raw & GAUGE_QEN != 0,
+      slpqmax: raw & GAUGE_SLPQMAX != 0,
+      nsfm: raw & GAUGE_NSFM != 0,
+      vdq: raw & GAUGE_VDQ != 0,
+      qmax: raw & GAUGE_QMAX != 0,
+      rx: raw & GAUGE_RX != 0,
+      ldmd: raw & GAUGE_LDMD != 0,
+      ocvfr: raw & GAUGE_OCVFR != 0,
+    }
+  }
+}
+
+/// ManufacturingStatus() flags
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ManufacturingStatus {
+  /// Precharge FET test
+  pub pchg_test: bool,
+  /// Charge FET test
+  pub chg_test: bool,
+  /// Discharge FET test
+  pub dsg_test: bool,
+  /// Impedance Track gauging is enabled
+  pub gauge_en: bool,
+  /// FETs are controlled by firmware
+  pub fet_en: bool,
+  /// Lifetime data collection is enabled
+  pub lf_en: bool,
+  /// Permanent failure is enabled
+  pub pf_en: bool,
+  /// Black box recorder is enabled
+  pub bbr_en: bool,
+  /// Fuse action is enabled
+  pub fuse_en: bool,
+  /// LED display is enabled
+  pub led_en: bool,
+  /// Calibration mode is enabled
+  pub cal_en: bool,
+}
+
+impl From<u32> for ManufacturingStatus {
+  fn from(raw: u32) -> Self {
+    ManufacturingStatus {
+      pchg_test: raw & MFG_PCHG_TEST != 0,
+      chg_test: raw & MFG_CHG_TEST != 0,
+      dsg_test: raw & MFG_DSG_TEST != 0,
+      gauge_en: raw & MFG_GAUGE_EN != 0,
+      fet_en: raw & MFG_FET_EN != 0,
+      lf_en: raw & MFG_LF_EN != 0,
+      pf_en: raw & MFG_PF_EN != 0,
+      bbr_en: raw & MFG_BBR_EN != 0,
+      fuse_en: raw & MFG_FUSE_EN != 0,
+      led_en: raw & MFG_LED_EN != 0,
+      cal_en: raw & MFG_CAL_EN != 0,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bq4050::{Address, Error, BQ4050};
+  use crate::mock::{MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+  const MAC: u8 = Address::Mac as u8;
+
+  // TRM bit position and the flag it maps to
+  type Field<T> = (u32, fn(&T) -> bool);
+
+  // Sets one bit at a time and checks that only the field at that TRM bit position follows it
+  fn check_bits<T>(decode: fn(u32) -> T, fields: &[Field<T>]) {
+    for &(bit, _) in fields {
+      let flags = decode(1 << bit);
+      for &(other, field) in fields {
+        assert_eq!(
+          field(&flags),
+          other == bit,
+          "bit {} set, bit {} read",
+          bit,
+          other
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn operation_status_bits() {
+    let fields: [Field<OperationStatus>; 28] = [
+      (0, |s| s.pres),
+      (1, |s| s.dsg),
+      (2, |s| s.chg),
+      (3, |s| s.pchg),
+      (5, |s| s.fuse),
+      (7, |s| s.btp_int),
+      (8, |s| s.security_mode == SecurityMode::FullAccess),
+      (9, |s| s.security_mode == SecurityMode::Unsealed),
+      (10, |s| s.sdv),
+      (11, |s| s.ss),
+      (12, |s| s.pf),
+      (13, |s| s.xdsg),
+      (14, |s| s.xchg),
+      (15, |s| s.sleep),
+      (16, |s| s.sdm),
+      (17, |s| s.led),
+      (18, |s| s.auth),
+      (19, |s| s.autocalm),
+      (20, |s| s.cal),
+      (21, |s| s.cal_offset),
+      (22, |s| s.xl),
+      (23, |s| s.sleepm),
+      (24, |s| s.init),
+      (25, |s| s.smblcal),
+      (26, |s| s.slpad),
+      (27, |s| s.slpcc),
+      (28, |s| s.cb),
+      (29, |s| s.emshut),
+    ];
+    check_bits(OperationStatus::from, &fields);
+
+    let cases = [
+      (0x0000_0000, SecurityMode::Reserved),
+      (0x0000_0100, SecurityMode::FullAccess),
+      (0x0000_0200, SecurityMode::Unsealed),
+      (0x0000_0300, SecurityMode::Sealed),
+    ];
+    for (raw, mode) in cases {
+      assert_eq!(OperationStatus::from(raw).security_mode, mode);
+    }
+  }
+
+  #[test]
+  fn charging_status_bits() {
+    let fields: [Field<ChargingStatus>; 15] = [
+      (0, |s| s.temperature_range == TemperatureRange::Under),
+      (1, |s| s.temperature_range == TemperatureRange::Low),
+      (2, |s| s.temperature_range == TemperatureRange::StandardLow),
+      (3, |s| s.temperature_range == TemperatureRange::Recommended),
+      (4, |s| s.temperature_range == TemperatureRange::StandardHigh),
+      (5, |s| s.temperature_range == TemperatureRange::High),
+      (6, |s| s.temperature_range == TemperatureRange::Over),
+      (8, |s| s.pv),
+      (9, |s| s.lv),
+      (10, |s| s.mv),
+      (11, |s| s.hv),
+      (12, |s| s.inhibit),
+      (13, |s| s.suspend),
+      (14, |s| s.mchg),
+      (15, |s| s.vct),
+    ];
+    check_bits(ChargingStatus::from, &fields);
+
+    assert_eq!(
+      ChargingStatus::from(0).temperature_range,
+      TemperatureRange::Unknown
+    );
+  }
+
+  #[test]
+  fn gauging_status_bits() {
+    let fields: [Field<GaugingStatus>; 19] = [
+      (0, |s| s.fd),
+      (1, |s| s.fc),
+      (2, |s| s.td),
+      (3, |s| s.tc),
+      (4, |s| s.bal_en),
+      (5, |s| s.edv),
+      (6, |s| s.dsg),
+      (7, |s| s.cf),
+      (8, |s| s.rest),
+      (10, |s| s.r_dis),
+      (11, |s| s.vok),
+      (12, |s| s.qen),
+      (13, |s| s.slpqmax),
+      (15, |s| s.nsfm),
+      (16, |s| s.vdq),
+      (17, |s| s.qmax),
+      (18, |s| s.rx),
+      (19, |s| s.ldmd),
+      (20, |s| s.ocvfr),
+    ];
+    check_bits(GaugingStatus::from, &fields);
+  }
+
+  #[test]
+  fn manufacturing_status_bits() {
+    let fields: [Field<ManufacturingStatus>; 11] = [
+      (0, |s| s.pchg_test),
+      (1, |s| s.chg_test),
+      (2, |s| s.dsg_test),
+      (3, |s| s.gauge_en),
+      (4, |s| s.fet_en),
+      (5, |s| s.lf_en),
+      (6, |s| s.pf_en),
+      (7, |s| s.bbr_en),
+      (8, |s| s.fuse_en),
+      (9, |s| s.led_en),
+      (15, |s| s.cal_en),
+    ];
+    check_bits(ManufacturingStatus::from, &fields);
+  }
+
+  #[test]
+  fn operation_status_read() {
+    let script = |reply: &[u8]| {
+      [
+        Transaction::write(DEV, &[MAC, 2, 0x54, 0x00]),
+        Transaction::write(DEV, &[MAC]),
+        Transaction::read(DEV, reply),
+      ]
+    };
+    let mut bq4050 = BQ4050::new(MockI2c::new(
+      &[
+        // SEALED with both FETs on, present and in SLEEP
+        script(&[6, 0x54, 0x00, 0x07, 0x83, 0x00, 0x00]),
+        // Only one data byte
+        script(&[3, 0x54, 0x00, 0x07]),
+      ]
+      .concat(),
+    ));
+
+    let status = bq4050.get_operation_status().unwrap();
+    assert!(status.pres && status.dsg && status.chg && status.sleep);
+    assert!(!status.pchg && !status.xdsg && !status.xchg);
+    assert_eq!(status.security_mode, SecurityMode::Sealed);
+    assert!(matches!(
+      bq4050.get_operation_status(),
+      Err(Error::InvalidLength(1))
+    ));
+    bq4050.release().done();
+  }
+}
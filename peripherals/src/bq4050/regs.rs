@@ -0,0 +1,166 @@
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+use super::{
+  minutes, BatteryMode, BatteryStatus, ChargingStatus, Cmd, CmdBlock, DaStatus1, DaStatus2,
+  DeciKelvin, Error, GaugingStatus, MacCmd, MilliAmps, MilliVolts, Minutes, OperationStatus,
+  Percent, PfFlags, SafetyFlags, BQ4050, DA_STATUS1_LEN, DA_STATUS2_LEN,
+};
+
+// Registers read by both the blocking `BQ4050` and the async `BQ4050Async`, paired with their decoders.
+// Drivers differ only in the transport, the decoding lives here.
+
+/// Word register
+pub(super) struct WordReg<T> {
+  pub(super) cmd: Cmd,
+  pub(super) decode: fn(u16) -> T,
+}
+
+/// Block register of exactly 4 bytes
+pub(super) struct FlagsReg<T> {
+  pub(super) cmd: CmdBlock,
+  pub(super) decode: fn(u32) -> T,
+}
+
+/// Block register of at least `min` bytes
+pub(super) struct BlockReg<T> {
+  pub(super) cmd: CmdBlock,
+  pub(super) min: usize,
+  pub(super) decode: fn(&[u8]) -> T,
+}
+
+/// MAC subcommand returning flags
+pub(super) struct MacFlagsReg<T> {
+  pub(super) cmd: MacCmd,
+  pub(super) decode: fn(u32) -> T,
+}
+
+pub(super) const BATTERY_MODE: WordReg<BatteryMode> = WordReg {
+  cmd: Cmd::BatteryModeReg,
+  decode: BatteryMode::from,
+};
+pub(super) const TEMPERATURE: WordReg<DeciKelvin> = WordReg {
+  cmd: Cmd::TemperatureReg,
+  decode: DeciKelvin,
+};
+pub(super) const VOLTAGE: WordReg<MilliVolts> = WordReg {
+  cmd: Cmd::VoltageReg,
+  decode: MilliVolts,
+};
+pub(super) const CURRENT: WordReg<MilliAmps> = WordReg {
+  cmd: Cmd::CurrentReg,
+  decode: |raw| MilliAmps(raw as i16),
+};
+pub(super) const AVERAGE_CURRENT: WordReg<MilliAmps> = WordReg {
+  cmd: Cmd::AverageCurrentReg,
+  decode: |raw| MilliAmps(raw as i16),
+};
+pub(super) const RELATIVE_SOC: WordReg<Percent> = WordReg {
+  cmd: Cmd::RelativeSocReg,
+  decode: Percent,
+};
+pub(super) const ABSOLUTE_SOC: WordReg<Percent> = WordReg {
+  cmd: Cmd::AbsoluteSocReg,
+  decode: Percent,
+};
+pub(super) const RUN_TIME_TO_EMPTY: WordReg<Option<Minutes>> = WordReg {
+  cmd: Cmd::RunTimeToEmptyReg,
+  decode: minutes,
+};
+pub(super) const AVERAGE_TIME_TO_EMPTY: WordReg<Option<Minutes>> = WordReg {
+  cmd: Cmd::AverageTimeToEmptyReg,
+  decode: minutes,
+};
+pub(super) const AVERAGE_TIME_TO_FULL: WordReg<Option<Minutes>> = WordReg {
+  cmd: Cmd::AverageTimeToFullReg,
+  decode: minutes,
+};
+pub(super) const BATTERY_STATUS: WordReg<BatteryStatus> = WordReg {
+  cmd: Cmd::BatteryStatusReg,
+  decode: BatteryStatus::from,
+};
+pub(super) const CYCLE_COUNT: WordReg<u16> = WordReg {
+  cmd: Cmd::CycleCountReg,
+  decode: |raw| raw,
+};
+pub(super) const CELL_VOLTAGE_1: WordReg<MilliVolts> = WordReg {
+  cmd: Cmd::CellVoltage1Reg,
+  decode: MilliVolts,
+};
+pub(super) const CELL_VOLTAGE_2: WordReg<MilliVolts> = WordReg {
+  cmd: Cmd::CellVoltage2Reg,
+  decode: MilliVolts,
+};
+pub(super) const CELL_VOLTAGE_3: WordReg<MilliVolts> = WordReg {
+  cmd: Cmd::CellVoltage3Reg,
+  decode: MilliVolts,
+};
+pub(super) const CELL_VOLTAGE_4: WordReg<MilliVolts> = WordReg {
+  cmd: Cmd::CellVoltage4Reg,
+  decode: MilliVolts,
+};
+
+pub(super) const SAFETY_ALERT: FlagsReg<SafetyFlags> = FlagsReg {
+  cmd: CmdBlock::SafetyAlertReg,
+  decode: SafetyFlags,
+};
+pub(super) const SAFETY_STATUS: FlagsReg<SafetyFlags> = FlagsReg {
+  cmd: CmdBlock::SafetyStatusReg,
+  decode: SafetyFlags,
+};
+pub(super) const PF_ALERT: FlagsReg<PfFlags> = FlagsReg {
+  cmd: CmdBlock::PfAlertReg,
+  decode: PfFlags,
+};
+pub(super) const PF_STATUS: FlagsReg<PfFlags> = FlagsReg {
+  cmd: CmdBlock::PfStatusReg,
+  decode: PfFlags,
+};
+
+pub(super) const DA_STATUS1: BlockReg<DaStatus1> = BlockReg {
+  cmd: CmdBlock::DaStatus1Reg,
+  min: DA_STATUS1_LEN,
+  decode: DaStatus1::from_block,
+};
+pub(super) const DA_STATUS2: BlockReg<DaStatus2> = BlockReg {
+  cmd: CmdBlock::DaStatus2Reg,
+  min: DA_STATUS2_LEN,
+  decode: DaStatus2::from_block,
+};
+
+pub(super) const OPERATION_STATUS: MacFlagsReg<OperationStatus> = MacFlagsReg {
+  cmd: MacCmd::OperationStatus,
+  decode: OperationStatus::from,
+};
+pub(super) const CHARGING_STATUS: MacFlagsReg<ChargingStatus> = MacFlagsReg {
+  cmd: MacCmd::ChargingStatus,
+  decode: ChargingStatus::from,
+};
+pub(super) const GAUGING_STATUS: MacFlagsReg<GaugingStatus> = MacFlagsReg {
+  cmd: MacCmd::GaugingStatus,
+  decode: GaugingStatus::from,
+};
+
+impl<I2C, I2cError> BQ4050<I2C>
+where
+  I2C: WriteRead<Error = I2cError> + Write<Error = I2cError> + Read<Error = I2cError>,
+{
+  pub(super) fn read_word_reg<T>(&mut self, reg: &WordReg<T>) -> Result<T, Error<I2cError>> {
+    Ok((reg.decode)(self.read_word(reg.cmd as u8)?))
+  }
+
+  pub(super) fn read_flags_reg<T>(&mut self, reg: &FlagsReg<T>) -> Result<T, Error<I2cError>> {
+    Ok((reg.decode)(self.read_block_u32(reg.cmd)?))
+  }
+
+  pub(super) fn read_block_reg<T>(&mut self, reg: &BlockReg<T>) -> Result<T, Error<I2cError>> {
+    let block = self.read_block_at_least(reg.cmd, reg.min)?;
+    Ok((reg.decode)(&block))
+  }
+
+  pub(super) fn read_mac_flags_reg<T>(
+    &mut self,
+    reg: &MacFlagsReg<T>,
+  ) -> Result<T, Error<I2cError>> {
+    Ok((reg.decode)(self.mac_read_flags(reg.cmd)?))
+  }
+}
@@ -0,0 +1,294 @@
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+use super::{Error, BQ4050};
+
+// Retries of transient failures, applied by the driver to every read transaction.
+// Writes are never repeated: a write whose reply got lost may have been executed already,
+// and commands like `MacCmd::ChgFet` toggle the state on every execution.
+// embedded-hal 0.2 I2C errors are opaque, the driver sorts them with the function set by
+// `BQ4050::set_error_classifier` as soon as the bus helpers get them.
+
+/// Kind of I2C error reported by the HAL
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BusErrorKind {
+  Nack,
+  Timeout,
+  Other,
+}
+
+/// Retry policy with exponential backoff
+#[derive(Copy, Clone)]
+pub struct RetryPolicy {
+  /// Attempts made after the first one failed
+  pub retries: u8,
+  /// Delay before the first retry, doubled on every next one
+  pub backoff_us: u32,
+  pub max_backoff_us: u32,
+  /// Busy waits for the given amount of microseconds
+  pub delay_us: fn(u32),
+}
+
+impl RetryPolicy {
+  /// Every error is returned right away
+  pub const NONE: RetryPolicy = RetryPolicy {
+    retries: 0,
+    backoff_us: 0,
+    max_backoff_us: 0,
+    delay_us: no_delay,
+  };
+
+  /// 3 retries starting at 1 ms backoff capped at 16 ms
+  pub const fn new(delay_us: fn(u32)) -> Self {
+    RetryPolicy {
+      retries: 3,
+      backoff_us: 1_000,
+      max_backoff_us: 16_000,
+      delay_us,
+    }
+  }
+
+  pub const fn retries(mut self, retries: u8) -> Self {
+    self.retries = retries;
+    self
+  }
+
+  pub const fn backoff(mut self, backoff_us: u32, max_backoff_us: u32) -> Self {
+    self.backoff_us = backoff_us;
+    self.max_backoff_us = max_backoff_us;
+    self
+  }
+}
+
+impl Default for RetryPolicy {
+  fn default() -> Self {
+    RetryPolicy::NONE
+  }
+}
+
+fn no_delay(_: u32) {}
+
+/// Leaves every HAL error as `Error::I2cError`
+pub(super) fn unclassified<E>(_: &E) -> BusErrorKind {
+  BusErrorKind::Other
+}
+
+impl<E> Error<E> {
+  /// Moves NACKs and timeouts of the HAL into their own variants
+  pub fn classify(self, classify: fn(&E) -> BusErrorKind) -> Self {
+    match self {
+      Error::I2cError(error) => match classify(&error) {
+        BusErrorKind::Nack => Error::Nack(error),
+        BusErrorKind::Timeout => Error::Timeout(error),
+        BusErrorKind::Other => Error::I2cError(error),
+      },
+      error => error,
+    }
+  }
+
+  /// Errors caused by the bus or a busy gauge, which may succeed if repeated
+  pub fn is_transient(&self) -> bool {
+    matches!(
+      self,
+      Error::I2cError(_)
+        | Error::Nack(_)
+        | Error::Timeout(_)
+        | Error::InvalidLength(_)
+        | Error::UnexpectedSubcommand(_)
+        | Error::PecMismatch
+    )
+  }
+}
+
+impl<I2C, I2cError> BQ4050<I2C>
+where
+  I2C: WriteRead<Error = I2cError> + Write<Error = I2cError> + Read<Error = I2cError>,
+{
+  /// Sets the function telling NACKs and timeouts of the HAL apart.
+  /// Every bus error is passed through it, getters return them as `Error::Nack` and `Error::Timeout`.
+  pub fn set_error_classifier(&mut self, classify: fn(&I2cError) -> BusErrorKind) {
+    self.classify = classify;
+  }
+
+  pub fn error_classifier(&self) -> fn(&I2cError) -> BusErrorKind {
+    self.classify
+  }
+
+  /// Sets retries of read transactions. `RetryPolicy::NONE` by default.
+  pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+    self.retry = policy;
+  }
+
+  pub fn retry_policy(&self) -> RetryPolicy {
+    self.retry
+  }
+
+  // Wraps an error of the HAL
+  pub(super) fn bus_error(&self, error: I2cError) -> Error<I2cError> {
+    Error::I2cError(error).classify(self.classify)
+  }
+
+  // Runs the read transaction `op` until it succeeds, fails with a non-transient error or retries run out.
+  // Only for reads, see the note at the top.
+  pub(super) fn retry_read<T>(
+    &mut self,
+    mut op: impl FnMut(&mut Self) -> Result<T, Error<I2cError>>,
+  ) -> Result<T, Error<I2cError>> {
+    let policy = self.retry;
+    let mut backoff_us = policy.backoff_us;
+    let mut attempt = 0;
+
+    loop {
+      let error = match op(self) {
+        Ok(value) => return Ok(value),
+        Err(error) => error,
+      };
+
+      if attempt >= policy.retries || !error.is_transient() {
+        return Err(error);
+      }
+
+      (policy.delay_us)(backoff_us);
+      backoff_us = backoff_us.saturating_mul(2).min(policy.max_backoff_us);
+      attempt += 1;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bq4050::{Address, Cmd, MacCmd};
+  use crate::mock::{MockError, MockI2c, Transaction};
+  use core::cell::Cell;
+
+  const DEV: u8 = Address::Dev as u8;
+  const MAC: u8 = Address::Mac as u8;
+  const TEMPERATURE: u8 = Cmd::TemperatureReg as u8;
+
+  std::thread_local! {
+    static DELAYED_US: Cell<u32> = const { Cell::new(0) };
+  }
+
+  fn delay_us(us: u32) {
+    DELAYED_US.with(|total| total.set(total.get() + us));
+  }
+
+  fn delayed_us() -> u32 {
+    DELAYED_US.with(Cell::get)
+  }
+
+  fn classify(e: &MockError) -> BusErrorKind {
+    match e {
+      MockError::Nack => BusErrorKind::Nack,
+      MockError::Timeout => BusErrorKind::Timeout,
+    }
+  }
+
+  fn bq4050(script: &[Transaction], policy: RetryPolicy) -> BQ4050<MockI2c> {
+    let mut bq4050 = BQ4050::new(MockI2c::new(script));
+    bq4050.set_error_classifier(classify);
+    bq4050.set_retry_policy(policy);
+    bq4050
+  }
+
+  #[test]
+  fn getters_return_classified_errors() {
+    let mut bq4050 = bq4050(
+      &[
+        Transaction::Fail(MockError::Nack),
+        Transaction::Fail(MockError::Timeout),
+      ],
+      RetryPolicy::NONE,
+    );
+
+    assert!(matches!(
+      bq4050.get_temperature(),
+      Err(Error::Nack(MockError::Nack))
+    ));
+    assert!(matches!(
+      bq4050.get_voltage(),
+      Err(Error::Timeout(MockError::Timeout))
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn unclassified_errors_by_default() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[Transaction::Fail(MockError::Nack)]));
+
+    assert!(matches!(
+      bq4050.get_temperature(),
+      Err(Error::I2cError(MockError::Nack))
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn getter_retries_with_backoff() {
+    let mut bq4050 = bq4050(
+      &[
+        Transaction::Fail(MockError::Nack),
+        Transaction::write(DEV, &[TEMPERATURE]),
+        Transaction::Fail(MockError::Timeout),
+        Transaction::write(DEV, &[TEMPERATURE]),
+        Transaction::read(DEV, &[0x94, 0x0B]),
+      ],
+      RetryPolicy::new(delay_us).backoff(1_000, 1_500),
+    );
+
+    assert_eq!(bq4050.get_temperature().unwrap().0, 0x0B94);
+    assert_eq!(delayed_us(), 1_000 + 1_500);
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn retries_run_out() {
+    let mut bq4050 = bq4050(
+      &[
+        Transaction::Fail(MockError::Timeout),
+        Transaction::Fail(MockError::Timeout),
+      ],
+      RetryPolicy::new(delay_us).retries(1),
+    );
+
+    assert!(matches!(
+      bq4050.get_temperature(),
+      Err(Error::Timeout(MockError::Timeout))
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn mac_read_repeats_subcommand() {
+    let mut bq4050 = bq4050(
+      &[
+        Transaction::write(DEV, &[MAC, 2, 0x01, 0x00]),
+        Transaction::write(DEV, &[MAC]),
+        Transaction::read(DEV, &[4, 0x02, 0x00, 0x50, 0x40]),
+        Transaction::write(DEV, &[MAC, 2, 0x01, 0x00]),
+        Transaction::write(DEV, &[MAC]),
+        Transaction::read(DEV, &[4, 0x01, 0x00, 0x50, 0x40]),
+      ],
+      RetryPolicy::new(delay_us).retries(1),
+    );
+
+    let mut buf = [0u8; 2];
+    assert_eq!(bq4050.mac_read(MacCmd::DeviceType, &mut buf).unwrap(), 2);
+    assert_eq!(buf, [0x50, 0x40]);
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn writes_are_not_retried() {
+    let mut bq4050 = bq4050(
+      &[Transaction::Fail(MockError::Nack)],
+      RetryPolicy::new(delay_us),
+    );
+
+    assert!(matches!(
+      bq4050.mac_write(MacCmd::ChgFet, &[]),
+      Err(Error::Nack(MockError::Nack))
+    ));
+    bq4050.release().done();
+  }
+}
@@ -0,0 +1,366 @@
+use core::fmt;
+
+// 13.1.x SafetyAlert() / SafetyStatus() and PFAlert() / PFStatus() bits.
+// Alert and status share the layout, alert is raised first and turns into status once the condition persists.
+
+/// Protection flag of SafetyAlert() and SafetyStatus()
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SafetyFlag {
+  Cuv = 0,
+  Cov = 1,
+  Occ1 = 2,
+  Occ2 = 3,
+  Ocd1 = 4,
+  Ocd2 = 5,
+  Aold = 6,
+  Aoldl = 7,
+  Ascc = 8,
+  Asccl = 9,
+  Ascd = 10,
+  Ascdl = 11,
+  Otc = 12,
+  Otd = 13,
+  Cuvc = 14,
+  Otf = 16,
+  Pto = 18,
+  Ptos = 19,
+  Cto = 20,
+  Ctos = 21,
+  Oc = 22,
+  Chgc = 23,
+  Chgv = 24,
+  Pchgc = 25,
+  Utc = 26,
+  Utd = 27,
+}
+
+impl SafetyFlag {
+  pub const ALL: [SafetyFlag; 26] = [
+    SafetyFlag::Cuv,
+    SafetyFlag::Cov,
+    SafetyFlag::Occ1,
+    SafetyFlag::Occ2,
+    SafetyFlag::Ocd1,
+    SafetyFlag::Ocd2,
+    SafetyFlag::Aold,
+    SafetyFlag::Aoldl,
+    SafetyFlag::Ascc,
+    SafetyFlag::Asccl,
+    SafetyFlag::Ascd,
+    SafetyFlag::Ascdl,
+    SafetyFlag::Otc,
+    SafetyFlag::Otd,
+    SafetyFlag::Cuvc,
+    SafetyFlag::Otf,
+    SafetyFlag::Pto,
+    SafetyFlag::Ptos,
+    SafetyFlag::Cto,
+    SafetyFlag::Ctos,
+    SafetyFlag::Oc,
+    SafetyFlag::Chgc,
+    SafetyFlag::Chgv,
+    SafetyFlag::Pchgc,
+    SafetyFlag::Utc,
+    SafetyFlag::Utd,
+  ];
+
+  pub fn description(&self) -> &'static str {
+    match self {
+      SafetyFlag::Cuv => "Cell undervoltage",
+      SafetyFlag::Cov => "Cell overvoltage",
+      SafetyFlag::Occ1 => "Overcurrent in charge 1st tier",
+      SafetyFlag::Occ2 => "Overcurrent in charge 2nd tier",
+      SafetyFlag::Ocd1 => "Overcurrent in discharge 1st tier",
+      SafetyFlag::Ocd2 => "Overcurrent in discharge 2nd tier",
+      SafetyFlag::Aold => "Overload in discharge",
+      SafetyFlag::Aoldl => "Overload in discharge latch",
+      SafetyFlag::Ascc => "Short circuit in charge",
+      SafetyFlag::Asccl => "Short circuit in charge latch",
+      SafetyFlag::Ascd => "Short circuit in discharge",
+      SafetyFlag::Ascdl => "Short circuit in discharge latch",
+      SafetyFlag::Otc => "Overtemperature in charge",
+      SafetyFlag::Otd => "Overtemperature in discharge",
+      SafetyFlag::Cuvc => "Cell undervoltage compensated for IR",
+      SafetyFlag::Otf => "FET overtemperature",
+      SafetyFlag::Pto => "Precharge timeout",
+      SafetyFlag::Ptos => "Precharge timeout suspend",
+      SafetyFlag::Cto => "Charge timeout",
+      SafetyFlag::Ctos => "Charge timeout suspend",
+      SafetyFlag::Oc => "Overcharge",
+      SafetyFlag::Chgc => "Overcharging current",
+      SafetyFlag::Chgv => "Overcharging voltage",
+      SafetyFlag::Pchgc => "Over-precharge current",
+      SafetyFlag::Utc => "Undertemperature in charge",
+      SafetyFlag::Utd => "Undertemperature in discharge",
+    }
+  }
+}
+
+impl fmt::Display for SafetyFlag {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.description())
+  }
+}
+
+/// Permanent failure flag of PFAlert() and PFStatus()
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PfFlag {
+  Suv = 0,
+  Sov = 1,
+  Socc = 2,
+  Socd = 3,
+  Sot = 4,
+  Sotf = 6,
+  Qim = 7,
+  Cb = 8,
+  Imp = 9,
+  Cd = 10,
+  Vimr = 11,
+  Vima = 12,
+  Cfetf = 16,
+  Dfetf = 17,
+  Fuse = 19,
+  Afer = 20,
+  Afec = 21,
+  SecondLevel = 22,
+  Opnc = 25,
+}
+
+impl PfFlag {
+  pub const ALL: [PfFlag; 19] = [
+    PfFlag::Suv,
+    PfFlag::Sov,
+    PfFlag::Socc,
+    PfFlag::Socd,
+    PfFlag::Sot,
+    PfFlag::Sotf,
+    PfFlag::Qim,
+    PfFlag::Cb,
+    PfFlag::Imp,
+    PfFlag::Cd,
+    PfFlag::Vimr,
+    PfFlag::Vima,
+    PfFlag::Cfetf,
+    PfFlag::Dfetf,
+    PfFlag::Fuse,
+    PfFlag::Afer,
+    PfFlag::Afec,
+    PfFlag::SecondLevel,
+    PfFlag::Opnc,
+  ];
+
+  pub fn description(&self) -> &'static str {
+    match self {
+      PfFlag::Suv => "Safety cell undervoltage failure",
+      PfFlag::Sov => "Safety cell overvoltage failure",
+      PfFlag::Socc => "Safety overcurrent in charge failure",
+      PfFlag::Socd => "Safety overcurrent in discharge failure",
+      PfFlag::Sot => "Safety overtemperature cell failure",
+      PfFlag::Sotf => "Safety overtemperature FET failure",
+      PfFlag::Qim => "QMax imbalance failure",
+      PfFlag::Cb => "Cell balancing failure",
+      PfFlag::Imp => "Impedance failure",
+      PfFlag::Cd => "Capacity degradation failure",
+      PfFlag::Vimr => "Voltage imbalance at rest failure",
+      PfFlag::Vima => "Voltage imbalance while pack is active failure",
+      PfFlag::Cfetf => "Charge FET failure",
+      PfFlag::Dfetf => "Discharge FET failure",
+      PfFlag::Fuse => "Chemical fuse failure",
+      PfFlag::Afer => "AFE register failure",
+      PfFlag::Afec => "AFE communication failure",
+      PfFlag::SecondLevel => "Second level protector failure",
+      PfFlag::Opnc => "Open cell tab connection failure",
+    }
+  }
+}
+
+impl fmt::Display for PfFlag {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.description())
+  }
+}
+
+/// Set of SafetyAlert() or SafetyStatus() flags
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SafetyFlags(pub u32);
+
+impl SafetyFlags {
+  pub fn contains(&self, flag: SafetyFlag) -> bool {
+    self.0 & (1 << flag as u32) != 0
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.iter().next().is_none()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = SafetyFlag> + '_ {
+    SafetyFlag::ALL
+      .into_iter()
+      .filter(|flag| self.contains(*flag))
+  }
+}
+
+/// Set of PFAlert() or PFStatus() flags
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PfFlags(pub u32);
+
+impl PfFlags {
+  pub fn contains(&self, flag: PfFlag) -> bool {
+    self.0 & (1 << flag as u32) != 0
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.iter().next().is_none()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = PfFlag> + '_ {
+    PfFlag::ALL.into_iter().filter(|flag| self.contains(*flag))
+  }
+}
+
+impl fmt::Display for SafetyFlags {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_list(f, self.iter())
+  }
+}
+
+impl fmt::Display for PfFlags {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_list(f, self.iter())
+  }
+}
+
+// Lists descriptions of all set flags separated by commas
+fn write_list<T: fmt::Display>(
+  f: &mut fmt::Formatter<'_>,
+  flags: impl Iterator<Item = T>,
+) -> fmt::Result {
+  for (i, flag) in flags.enumerate() {
+    if i > 0 {
+      f.write_str(", ")?;
+    }
+    write!(f, "{}", flag)?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::string::ToString;
+  use std::vec::Vec;
+
+  use super::*;
+
+  // Bit positions from the SafetyAlert() table
+  const SAFETY_BITS: [(SafetyFlag, u32); 26] = [
+    (SafetyFlag::Cuv, 0),
+    (SafetyFlag::Cov, 1),
+    (SafetyFlag::Occ1, 2),
+    (SafetyFlag::Occ2, 3),
+    (SafetyFlag::Ocd1, 4),
+    (SafetyFlag::Ocd2, 5),
+    (SafetyFlag::Aold, 6),
+    (SafetyFlag::Aoldl, 7),
+    (SafetyFlag::Ascc, 8),
+    (SafetyFlag::Asccl, 9),
+    (SafetyFlag::Ascd, 10),
+    (SafetyFlag::Ascdl, 11),
+    (SafetyFlag::Otc, 12),
+    (SafetyFlag::Otd, 13),
+    (SafetyFlag::Cuvc, 14),
+    (SafetyFlag::Otf, 16),
+    (SafetyFlag::Pto, 18),
+    (SafetyFlag::Ptos, 19),
+    (SafetyFlag::Cto, 20),
+    (SafetyFlag::Ctos, 21),
+    (SafetyFlag::Oc, 22),
+    (SafetyFlag::Chgc, 23),
+    (SafetyFlag::Chgv, 24),
+    (SafetyFlag::Pchgc, 25),
+    (SafetyFlag::Utc, 26),
+    (SafetyFlag::Utd, 27),
+  ];
+
+  // Bit positions from the PFAlert() table
+  const PF_BITS: [(PfFlag, u32); 19] = [
+    (PfFlag::Suv, 0),
+    (PfFlag::Sov, 1),
+    (PfFlag::Socc, 2),
+    (PfFlag::Socd, 3),
+    (PfFlag::Sot, 4),
+    (PfFlag::Sotf, 6),
+    (PfFlag::Qim, 7),
+    (PfFlag::Cb, 8),
+    (PfFlag::Imp, 9),
+    (PfFlag::Cd, 10),
+    (PfFlag::Vimr, 11),
+    (PfFlag::Vima, 12),
+    (PfFlag::Cfetf, 16),
+    (PfFlag::Dfetf, 17),
+    (PfFlag::Fuse, 19),
+    (PfFlag::Afer, 20),
+    (PfFlag::Afec, 21),
+    (PfFlag::SecondLevel, 22),
+    (PfFlag::Opnc, 25),
+  ];
+
+  #[test]
+  fn safety_bits() {
+    assert_eq!(
+      SafetyFlag::ALL.map(|flag| flag as u32),
+      SAFETY_BITS.map(|(_, bit)| bit)
+    );
+
+    for (flag, bit) in SAFETY_BITS {
+      let flags = SafetyFlags(1 << bit);
+      assert!(flags.contains(flag), "{:?}", flag);
+      assert_eq!(flags.iter().collect::<Vec<_>>(), [flag]);
+    }
+  }
+
+  #[test]
+  fn pf_bits() {
+    assert_eq!(
+      PfFlag::ALL.map(|flag| flag as u32),
+      PF_BITS.map(|(_, bit)| bit)
+    );
+
+    for (flag, bit) in PF_BITS {
+      let flags = PfFlags(1 << bit);
+      assert!(flags.contains(flag), "{:?}", flag);
+      assert_eq!(flags.iter().collect::<Vec<_>>(), [flag]);
+    }
+  }
+
+  #[test]
+  fn reserved_bits_are_ignored() {
+    let safety = SafetyFlags(1 << 15 | 1 << 17 | 0xF000_0000);
+    let pf = PfFlags(1 << 5 | 0x0000_E000 | 1 << 18 | 1 << 23 | 1 << 24 | 0xFC00_0000);
+
+    assert!(safety.is_empty());
+    assert!(pf.is_empty());
+    assert_eq!(safety.to_string(), "");
+  }
+
+  #[test]
+  fn decode_and_display() {
+    // CUV, OTC and UTD of SafetyStatus(), SOV and DFETF of PFStatus()
+    let safety = SafetyFlags(u32::from_le_bytes([0x01, 0x10, 0x00, 0x08]));
+    let pf = PfFlags(u32::from_le_bytes([0x02, 0x00, 0x02, 0x00]));
+
+    assert_eq!(
+      safety.iter().collect::<Vec<_>>(),
+      [SafetyFlag::Cuv, SafetyFlag::Otc, SafetyFlag::Utd]
+    );
+    assert!(!safety.contains(SafetyFlag::Cov));
+    assert_eq!(
+      safety.to_string(),
+      "Cell undervoltage, Overtemperature in charge, Undertemperature in discharge"
+    );
+    assert_eq!(pf.iter().collect::<Vec<_>>(), [PfFlag::Sov, PfFlag::Dfetf]);
+    assert_eq!(
+      pf.to_string(),
+      "Safety cell overvoltage failure, Discharge FET failure"
+    );
+  }
+}
@@ -0,0 +1,163 @@
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+use super::{Error, MacCmd, SecurityMode, BQ4050};
+
+// Security mode transitions, see 13.1 SealDevice(), SecurityKeys() and OperationStatus()[SEC1, SEC0].
+// Keys are 32-bit values sent as two ManufacturerAccess() words, low word first.
+// TI defaults are 0x36720414 for unseal and 0xFFFFFFFF for full access.
+
+// Time the gauge needs to process a key or SealDevice() before OperationStatus() shows the new mode
+const SECURITY_DELAY_US: u32 = 1_000;
+
+impl<I2C, I2cError> BQ4050<I2C>
+where
+  I2C: WriteRead<Error = I2cError> + Write<Error = I2cError> + Read<Error = I2cError>,
+{
+  /// Moves the gauge from SEALED to UNSEALED mode
+  pub fn unseal(
+    &mut self,
+    key: u32,
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<(), Error<I2cError>> {
+    self.send_key(key)?;
+    delay_source.delay_us(SECURITY_DELAY_US);
+    self.expect_security_mode(&[SecurityMode::Unsealed, SecurityMode::FullAccess])
+  }
+
+  /// Moves the gauge from UNSEALED to FULL ACCESS mode
+  pub fn full_access(
+    &mut self,
+    key: u32,
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<(), Error<I2cError>> {
+    self.send_key(key)?;
+    delay_source.delay_us(SECURITY_DELAY_US);
+    self.expect_security_mode(&[SecurityMode::FullAccess])
+  }
+
+  /// Moves the gauge to SEALED mode
+  pub fn seal(&mut self, delay_source: &mut impl DelayUs<u32>) -> Result<(), Error<I2cError>> {
+    self.mac_write(MacCmd::SealDevice, &[])?;
+    delay_source.delay_us(SECURITY_DELAY_US);
+
+    match self.security_mode()? {
+      SecurityMode::Sealed => Ok(()),
+      mode => Err(Error::SealFailed(mode)),
+    }
+  }
+
+  fn send_key(&mut self, key: u32) -> Result<(), Error<I2cError>> {
+    self.mac_write(key as u16, &[])?;
+    self.mac_write((key >> 16) as u16, &[])
+  }
+
+  fn expect_security_mode(&mut self, expected: &[SecurityMode]) -> Result<(), Error<I2cError>> {
+    let mode = self.security_mode()?;
+    if expected.contains(&mode) {
+      Ok(())
+    } else {
+      Err(Error::KeyRejected(mode))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bq4050::Address;
+  use crate::mock::{MockDelay, MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+  const MAC: u8 = Address::Mac as u8;
+
+  const UNSEAL_KEY: u32 = 0x3672_0414;
+  const FULL_ACCESS_KEY: u32 = 0xFFFF_FFFF;
+
+  fn mac_cmd(subcommand: u16) -> Transaction {
+    let [low, high] = subcommand.to_le_bytes();
+    Transaction::write(DEV, &[MAC, 2, low, high])
+  }
+
+  // OperationStatus() read with SEC1, SEC0 (bits 9, 8) set to `sec`
+  fn operation_status(sec: u8) -> [Transaction; 3] {
+    [
+      mac_cmd(0x0054),
+      Transaction::write(DEV, &[MAC]),
+      Transaction::read(DEV, &[6, 0x54, 0x00, 0x00, sec, 0x00, 0x00]),
+    ]
+  }
+
+  fn script(keys: &[u16], sec: u8) -> std::vec::Vec<Transaction> {
+    let mut script: std::vec::Vec<_> = keys.iter().map(|&key| mac_cmd(key)).collect();
+    script.extend(operation_status(sec));
+    script
+  }
+
+  #[test]
+  fn unseal_sends_low_word_first() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script(&[0x0414, 0x3672], 0b10)));
+    let mut delay = MockDelay::default();
+
+    bq4050.unseal(UNSEAL_KEY, &mut delay).unwrap();
+    assert_eq!(delay.total_us, SECURITY_DELAY_US);
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn unseal_accepts_full_access() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script(&[0x0414, 0x3672], 0b01)));
+
+    bq4050
+      .unseal(UNSEAL_KEY, &mut MockDelay::default())
+      .unwrap();
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn unseal_rejected_key() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script(&[0x0414, 0x3672], 0b11)));
+
+    assert!(matches!(
+      bq4050.unseal(UNSEAL_KEY, &mut MockDelay::default()),
+      Err(Error::KeyRejected(SecurityMode::Sealed))
+    ));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn full_access_checks_mode() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(
+      &[
+        script(&[0xFFFF, 0xFFFF], 0b01),
+        script(&[0xFFFF, 0xFFFF], 0b10),
+      ]
+      .concat(),
+    ));
+    let mut delay = MockDelay::default();
+
+    bq4050.full_access(FULL_ACCESS_KEY, &mut delay).unwrap();
+    assert!(matches!(
+      bq4050.full_access(FULL_ACCESS_KEY, &mut delay),
+      Err(Error::KeyRejected(SecurityMode::Unsealed))
+    ));
+    assert_eq!(delay.total_us, 2 * SECURITY_DELAY_US);
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn seal_checks_mode() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(
+      &[script(&[0x0030], 0b11), script(&[0x0030], 0b10)].concat(),
+    ));
+    let mut delay = MockDelay::default();
+
+    bq4050.seal(&mut delay).unwrap();
+    assert!(matches!(
+      bq4050.seal(&mut delay),
+      Err(Error::SealFailed(SecurityMode::Unsealed))
+    ));
+    assert_eq!(delay.total_us, 2 * SECURITY_DELAY_US);
+    bq4050.release().done();
+  }
+}
@@ -0,0 +1,391 @@
+use byteorder::{ByteOrder, LittleEndian};
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+use super::{Address, Error, BQ4050, MAC_BLOCK_MAX};
+
+// SMBus transactions with optional PEC (packet error checking).
+// PEC is CRC-8 (x^8 + x^2 + x + 1) over every byte of the transaction including the address bytes.
+// Framing is kept in free functions shared by the blocking and async drivers.
+
+const PEC_POLY: u8 = 0x07;
+
+// Length byte, up to MAC_BLOCK_MAX + 2 bytes of MAC reply and PEC
+pub(super) const BLOCK_READ_MAX: usize = MAC_BLOCK_MAX + 4;
+// Command, length byte, up to MAC_BLOCK_MAX + 2 bytes of MAC request and PEC
+pub(super) const BLOCK_WRITE_MAX: usize = MAC_BLOCK_MAX + 5;
+
+fn crc8(crc: u8, data: &[u8]) -> u8 {
+  data.iter().fold(crc, |crc, byte| {
+    let mut crc = crc ^ byte;
+    for _ in 0..8 {
+      crc = if crc & 0x80 != 0 {
+        (crc << 1) ^ PEC_POLY
+      } else {
+        crc << 1
+      };
+    }
+    crc
+  })
+}
+
+const fn write_address() -> u8 {
+  (Address::Dev as u8) << 1
+}
+
+const fn read_address() -> u8 {
+  ((Address::Dev as u8) << 1) | 1
+}
+
+pub(super) fn word_read_len(pec: bool) -> usize {
+  if pec {
+    3
+  } else {
+    2
+  }
+}
+
+pub(super) fn decode_word<E>(pec: bool, cmd: u8, reply: &[u8]) -> Result<u16, Error<E>> {
+  if pec {
+    let crc = crc8(0, &[write_address(), cmd, read_address()]);
+    if crc8(crc, &reply[0..2]) != reply[2] {
+      return Err(Error::PecMismatch);
+    }
+  }
+
+  Ok(LittleEndian::read_u16(&reply[0..2]))
+}
+
+// Returns amount of bytes to send
+pub(super) fn encode_word(pec: bool, cmd: u8, value: u16, buffer: &mut [u8; 4]) -> usize {
+  buffer[0] = cmd;
+  LittleEndian::write_u16(&mut buffer[1..3], value);
+
+  if pec {
+    buffer[3] = crc8(crc8(0, &[write_address()]), &buffer[0..3]);
+    4
+  } else {
+    3
+  }
+}
+
+// Amount of bytes to read for a block reply fitting into `buf_len`
+pub(super) fn block_read_len(pec: bool, buf_len: usize) -> usize {
+  (buf_len + block_overhead(pec)).min(BLOCK_READ_MAX)
+}
+
+fn block_overhead(pec: bool) -> usize {
+  if pec {
+    2
+  } else {
+    1
+  }
+}
+
+// Copies block data from `reply` into `buf`. Returns the length reported by the device.
+pub(super) fn decode_block<E>(
+  pec: bool,
+  cmd: u8,
+  reply: &[u8],
+  buf: &mut [u8],
+) -> Result<usize, Error<E>> {
+  let len = reply[0] as usize;
+  if len > reply.len() - block_overhead(pec) {
+    return Err(Error::InvalidLength(reply[0]));
+  }
+
+  if pec {
+    let crc = crc8(0, &[write_address(), cmd, read_address()]);
+    if crc8(crc, &reply[..1 + len]) != reply[1 + len] {
+      return Err(Error::PecMismatch);
+    }
+  }
+
+  buf[..len].copy_from_slice(&reply[1..1 + len]);
+  Ok(len)
+}
+
+// Returns amount of bytes to send
+pub(super) fn encode_block<E>(
+  pec: bool,
+  cmd: u8,
+  data: &[u8],
+  buffer: &mut [u8; BLOCK_WRITE_MAX],
+) -> Result<usize, Error<E>> {
+  if data.len() > MAC_BLOCK_MAX + 2 {
+    return Err(Error::InvalidLength(data.len() as u8));
+  }
+
+  buffer[0] = cmd;
+  buffer[1] = data.len() as u8;
+  buffer[2..2 + data.len()].copy_from_slice(data);
+
+  let mut len = 2 + data.len();
+  if pec {
+    buffer[len] = crc8(crc8(0, &[write_address()]), &buffer[..len]);
+    len += 1;
+  }
+
+  Ok(len)
+}
+
+// Prepends subcommand to MAC request data. Returns request length.
+pub(super) fn encode_mac<E>(
+  subcommand: u16,
+  data: &[u8],
+  buffer: &mut [u8; MAC_BLOCK_MAX + 2],
+) -> Result<usize, Error<E>> {
+  if data.len() > MAC_BLOCK_MAX {
+    return Err(Error::InvalidLength(data.len() as u8));
+  }
+
+  LittleEndian::write_u16(&mut buffer[0..2], subcommand);
+  buffer[2..2 + data.len()].copy_from_slice(data);
+  Ok(2 + data.len())
+}
+
+// Checks subcommand echo of MAC reply and copies the data into `buf`. Returns amount of bytes copied.
+pub(super) fn decode_mac<E>(
+  subcommand: u16,
+  reply: &[u8],
+  buf: &mut [u8],
+) -> Result<usize, Error<E>> {
+  if reply.len() < 2 {
+    return Err(Error::InvalidLength(reply.len() as u8));
+  }
+
+  let echo = LittleEndian::read_u16(&reply[0..2]);
+  if echo != subcommand {
+    return Err(Error::UnexpectedSubcommand(echo));
+  }
+
+  let data = &reply[2..];
+  let copied = data.len().min(buf.len());
+  buf[..copied].copy_from_slice(&data[..copied]);
+  Ok(copied)
+}
+
+// Reads 2 to 4 bytes long MAC flags reply as u32
+pub(super) fn decode_flags<E>(data: &[u8; 4], len: usize) -> Result<u32, Error<E>> {
+  if len < 2 {
+    return Err(Error::InvalidLength(len as u8));
+  }
+
+  Ok(LittleEndian::read_u32(data))
+}
+
+impl<I2C, I2cError> BQ4050<I2C>
+where
+  I2C: WriteRead<Error = I2cError> + Write<Error = I2cError> + Read<Error = I2cError>,
+{
+  pub(super) fn read_word(&mut self, cmd: u8) -> Result<u16, Error<I2cError>> {
+    self.retry_read(|bq| {
+      let mut buffer = [0u8; 3];
+      let read = word_read_len(bq.pec);
+      bq.command_read(cmd, &mut buffer[..read])?;
+
+      decode_word(bq.pec, cmd, &buffer)
+    })
+  }
+
+  pub(super) fn write_word(&mut self, cmd: u8, value: u16) -> Result<(), Error<I2cError>> {
+    let mut buffer = [0u8; 4];
+    let len = encode_word(self.pec, cmd, value, &mut buffer);
+
+    self
+      .i2c
+      .write(Address::Dev as u8, &buffer[..len])
+      .map_err(|e| self.bus_error(e))
+  }
+
+  // Reads SMBus block into `buf` honoring the length byte. Returns the length reported by the device.
+  pub(super) fn read_block_raw(
+    &mut self,
+    cmd: u8,
+    buf: &mut [u8],
+  ) -> Result<usize, Error<I2cError>> {
+    self.retry_read(|bq| bq.read_block_once(cmd, buf))
+  }
+
+  // Single attempt of `read_block_raw`
+  pub(super) fn read_block_once(
+    &mut self,
+    cmd: u8,
+    buf: &mut [u8],
+  ) -> Result<usize, Error<I2cError>> {
+    let mut block = [0u8; BLOCK_READ_MAX];
+    let read = block_read_len(self.pec, buf.len());
+    self.command_read(cmd, &mut block[..read])?;
+
+    decode_block(self.pec, cmd, &block[..read], buf)
+  }
+
+  // Writes the command and reads its data. Baseline firmware read the gauge with STOP between
+  // the two transactions, repeated start is used if enabled with `BQ4050::set_repeated_start`.
+  // PEC covers the whole write-read sequence, so it always reads with repeated start.
+  fn command_read(&mut self, cmd: u8, buffer: &mut [u8]) -> Result<(), Error<I2cError>> {
+    if self.repeated_start || self.pec {
+      self
+        .i2c
+        .write_read(Address::Dev as u8, &[cmd], buffer)
+        .map_err(|e| self.bus_error(e))
+    } else {
+      self.int_rw(Address::Dev as u8, &[cmd], buffer)
+    }
+  }
+
+  fn int_rw(
+    &mut self,
+    address: u8,
+    bytes: &[u8],
+    buffer: &mut [u8],
+  ) -> Result<(), Error<I2cError>> {
+    self
+      .i2c
+      .write(address, bytes)
+      .map_err(|e| self.bus_error(e))?;
+    self
+      .i2c
+      .read(address, buffer)
+      .map_err(|e| self.bus_error(e))
+  }
+
+  pub(super) fn write_block_raw(&mut self, cmd: u8, data: &[u8]) -> Result<(), Error<I2cError>> {
+    let mut buffer = [0u8; BLOCK_WRITE_MAX];
+    let len = encode_block(self.pec, cmd, data, &mut buffer)?;
+
+    self
+      .i2c
+      .write(Address::Dev as u8, &buffer[..len])
+      .map_err(|e| self.bus_error(e))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  type Error = super::Error<()>;
+
+  const TEMPERATURE: u8 = 0x08;
+  const REMAINING_CAPACITY_ALARM: u8 = 0x01;
+  const MANUFACTURER_NAME: u8 = 0x20;
+  const MAC: u8 = Address::Mac as u8;
+
+  #[test]
+  fn crc8_check_value() {
+    assert_eq!(crc8(0, b"123456789"), 0xF4);
+  }
+
+  #[test]
+  fn read_word_pec() {
+    let reply = [0x94, 0x0B, 0xF9];
+    assert_eq!(
+      decode_word::<()>(true, TEMPERATURE, &reply).unwrap(),
+      0x0B94
+    );
+
+    let mut corrupted = reply;
+    corrupted[0] ^= 0x01;
+    assert!(matches!(
+      decode_word::<()>(true, TEMPERATURE, &corrupted),
+      Err(Error::PecMismatch)
+    ));
+  }
+
+  #[test]
+  fn read_word_without_pec() {
+    assert_eq!(
+      decode_word::<()>(false, TEMPERATURE, &[0x94, 0x0B, 0x00]).unwrap(),
+      0x0B94
+    );
+  }
+
+  #[test]
+  fn write_word_pec() {
+    let mut buffer = [0u8; 4];
+    let len = encode_word(true, REMAINING_CAPACITY_ALARM, 0x012C, &mut buffer);
+    assert_eq!(
+      &buffer[..len],
+      &[REMAINING_CAPACITY_ALARM, 0x2C, 0x01, 0x2D]
+    );
+
+    let mut buffer = [0u8; 4];
+    let len = encode_word(true, REMAINING_CAPACITY_ALARM, 0x012D, &mut buffer);
+    assert_ne!(buffer[len - 1], 0x2D);
+  }
+
+  #[test]
+  fn read_block_pec() {
+    let reply = [2, b'T', b'I', 0x7F];
+    let mut buf = [0u8; 2];
+    assert_eq!(
+      decode_block::<()>(true, MANUFACTURER_NAME, &reply, &mut buf).unwrap(),
+      2
+    );
+    assert_eq!(&buf, b"TI");
+
+    let mut corrupted = reply;
+    corrupted[2] ^= 0x80;
+    assert!(matches!(
+      decode_block::<()>(true, MANUFACTURER_NAME, &corrupted, &mut buf),
+      Err(Error::PecMismatch)
+    ));
+  }
+
+  #[test]
+  fn read_block_length_out_of_reply() {
+    let mut buf = [0u8; 4];
+    assert!(matches!(
+      decode_block::<()>(true, MANUFACTURER_NAME, &[4, b'T', b'I', 0x7F], &mut buf),
+      Err(Error::InvalidLength(4))
+    ));
+  }
+
+  #[test]
+  fn read_word_transfer() {
+    use crate::bq4050::BQ4050;
+    use crate::mock::{MockI2c, Transaction};
+
+    let dev = Address::Dev as u8;
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[
+      Transaction::write(dev, &[TEMPERATURE]),
+      Transaction::read(dev, &[0x94, 0x0B]),
+      Transaction::write_read(dev, &[TEMPERATURE], &[0x94, 0x0B]),
+    ]));
+
+    assert_eq!(bq4050.read_word(TEMPERATURE).unwrap(), 0x0B94);
+    bq4050.set_repeated_start(true);
+    assert_eq!(bq4050.read_word(TEMPERATURE).unwrap(), 0x0B94);
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn read_word_transfer_pec() {
+    use crate::bq4050::BQ4050;
+    use crate::mock::{MockI2c, Transaction};
+
+    // PEC is computed over the write and the read, both modes use repeated start
+    let dev = Address::Dev as u8;
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[
+      Transaction::write_read(dev, &[TEMPERATURE], &[0x94, 0x0B, 0xF9]),
+      Transaction::write_read(dev, &[TEMPERATURE], &[0x94, 0x0B, 0xF9]),
+    ]));
+    bq4050.set_pec(true);
+
+    assert_eq!(bq4050.read_word(TEMPERATURE).unwrap(), 0x0B94);
+    bq4050.set_repeated_start(true);
+    assert_eq!(bq4050.read_word(TEMPERATURE).unwrap(), 0x0B94);
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn write_block_pec() {
+    let mut buffer = [0u8; BLOCK_WRITE_MAX];
+    let len = encode_block::<()>(true, MAC, &[0x01, 0x00], &mut buffer).unwrap();
+    assert_eq!(&buffer[..len], &[MAC, 2, 0x01, 0x00, 0x79]);
+
+    let mut buffer = [0u8; BLOCK_WRITE_MAX];
+    let len = encode_block::<()>(true, MAC, &[0x01, 0x01], &mut buffer).unwrap();
+    assert_ne!(buffer[len - 1], 0x79);
+  }
+}
@@ -0,0 +1,431 @@
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+use super::{
+  BatteryStatus, Capacity, DaStatus2, DeciKelvin, Error, MilliAmps, MilliVolts, Minutes,
+  OperationStatus, Percent, BQ4050,
+};
+
+// Periodic polling of the values a pack monitor usually shows.
+// The driver has no clock, timestamps are passed in by the caller in any monotonic unit (ticks, ms, s).
+
+/// Value polled by `SnapshotPoller`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotField {
+  Voltage = 0,
+  Current = 1,
+  AverageCurrent = 2,
+  StateOfCharge = 3,
+  RemainingCapacity = 4,
+  FullChargeCapacity = 5,
+  CellVoltages = 6,
+  Temperature = 7,
+  Temperatures = 8,
+  TimeToEmpty = 9,
+  TimeToFull = 10,
+  BatteryStatus = 11,
+  OperationStatus = 12,
+}
+
+impl SnapshotField {
+  pub const ALL: [SnapshotField; 13] = [
+    SnapshotField::Voltage,
+    SnapshotField::Current,
+    SnapshotField::AverageCurrent,
+    SnapshotField::StateOfCharge,
+    SnapshotField::RemainingCapacity,
+    SnapshotField::FullChargeCapacity,
+    SnapshotField::CellVoltages,
+    SnapshotField::Temperature,
+    SnapshotField::Temperatures,
+    SnapshotField::TimeToEmpty,
+    SnapshotField::TimeToFull,
+    SnapshotField::BatteryStatus,
+    SnapshotField::OperationStatus,
+  ];
+}
+
+/// Set of polled fields
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SnapshotFields(pub u16);
+
+impl SnapshotFields {
+  pub const NONE: SnapshotFields = SnapshotFields(0);
+  pub const ALL: SnapshotFields = SnapshotFields((1 << SnapshotField::ALL.len()) - 1);
+
+  pub const fn with(self, field: SnapshotField) -> Self {
+    SnapshotFields(self.0 | (1 << field as u16))
+  }
+
+  pub fn contains(&self, field: SnapshotField) -> bool {
+    self.0 & (1 << field as u16) != 0
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = SnapshotField> + '_ {
+    SnapshotField::ALL
+      .into_iter()
+      .filter(|field| self.contains(*field))
+  }
+}
+
+/// Last polled value of a field
+#[derive(Copy, Clone, Debug)]
+pub struct Reading<T, E> {
+  /// Last successfully read value, kept when later reads fail
+  pub value: Option<T>,
+  /// Timestamp of `value`
+  pub updated: Option<u32>,
+  /// Timestamp of the last read, successful or not
+  pub polled: Option<u32>,
+  /// Error of the last read, cleared once a read succeeds
+  pub error: Option<Error<E>>,
+}
+
+impl<T, E> Default for Reading<T, E> {
+  fn default() -> Self {
+    Reading {
+      value: None,
+      updated: None,
+      polled: None,
+      error: None,
+    }
+  }
+}
+
+impl<T: Copy, E> Reading<T, E> {
+  /// Time passed since `value` was read
+  pub fn age(&self, now: u32) -> Option<u32> {
+    self.updated.map(|updated| now.wrapping_sub(updated))
+  }
+
+  /// Value read no more than `max_age` ago
+  pub fn fresh(&self, now: u32, max_age: u32) -> Option<T> {
+    match self.age(now) {
+      Some(age) if age <= max_age => self.value,
+      _ => None,
+    }
+  }
+
+  // Error of the last read with its timestamp
+  fn failure(&self) -> Option<(u32, &Error<E>)> {
+    Some((self.polled?, self.error.as_ref()?))
+  }
+
+  fn update(&mut self, result: Result<T, Error<E>>, now: u32) -> bool {
+    self.polled = Some(now);
+    match result {
+      Ok(value) => {
+        self.value = Some(value);
+        self.updated = Some(now);
+        self.error = None;
+        true
+      }
+      Err(error) => {
+        self.error = Some(error);
+        false
+      }
+    }
+  }
+}
+
+/// Pack state gathered by `SnapshotPoller`. Fields not selected for polling stay empty
+#[derive(Copy, Clone, Debug)]
+pub struct BatterySnapshot<E> {
+  pub voltage: Reading<MilliVolts, E>,
+  pub current: Reading<MilliAmps, E>,
+  pub average_current: Reading<MilliAmps, E>,
+  /// RelativeStateOfCharge()
+  pub state_of_charge: Reading<Percent, E>,
+  pub remaining_capacity: Reading<Capacity, E>,
+  pub full_charge_capacity: Reading<Capacity, E>,
+  /// Cell 1 first
+  pub cell_voltages: Reading<[MilliVolts; 4], E>,
+  /// Temperature() as configured by the gauge
+  pub temperature: Reading<DeciKelvin, E>,
+  /// Every sensor, from DAStatus2()
+  pub temperatures: Reading<DaStatus2, E>,
+  /// RunTimeToEmpty(), `None` while not discharging
+  pub time_to_empty: Reading<Option<Minutes>, E>,
+  /// AverageTimeToFull(), `None` while not charging
+  pub time_to_full: Reading<Option<Minutes>, E>,
+  pub battery_status: Reading<BatteryStatus, E>,
+  pub operation_status: Reading<OperationStatus, E>,
+  // Index into `SnapshotField::ALL` where `SnapshotPoller::poll_next` continues
+  next_field: usize,
+}
+
+// Derive would require `E: Default`
+impl<E> Default for BatterySnapshot<E> {
+  fn default() -> Self {
+    BatterySnapshot {
+      voltage: Reading::default(),
+      current: Reading::default(),
+      average_current: Reading::default(),
+      state_of_charge: Reading::default(),
+      remaining_capacity: Reading::default(),
+      full_charge_capacity: Reading::default(),
+      cell_voltages: Reading::default(),
+      temperature: Reading::default(),
+      temperatures: Reading::default(),
+      time_to_empty: Reading::default(),
+      time_to_full: Reading::default(),
+      battery_status: Reading::default(),
+      operation_status: Reading::default(),
+      next_field: 0,
+    }
+  }
+}
+
+impl<E> BatterySnapshot<E> {
+  /// Errors of the last read of every field
+  pub fn errors(&self) -> impl Iterator<Item = &Error<E>> {
+    self.errors_with_time().map(|(_, error)| error)
+  }
+
+  /// Errors of the fields read at `now`, older ones of fields skipped by `SnapshotPoller::poll_next` are left out
+  pub fn errors_at(&self, now: u32) -> impl Iterator<Item = &Error<E>> {
+    self
+      .errors_with_time()
+      .filter(move |(polled, _)| *polled == now)
+      .map(|(_, error)| error)
+  }
+
+  fn errors_with_time(&self) -> impl Iterator<Item = (u32, &Error<E>)> {
+    [
+      self.voltage.failure(),
+      self.current.failure(),
+      self.average_current.failure(),
+      self.state_of_charge.failure(),
+      self.remaining_capacity.failure(),
+      self.full_charge_capacity.failure(),
+      self.cell_voltages.failure(),
+      self.temperature.failure(),
+      self.temperatures.failure(),
+      self.time_to_empty.failure(),
+      self.time_to_full.failure(),
+      self.battery_status.failure(),
+      self.operation_status.failure(),
+    ]
+    .into_iter()
+    .flatten()
+  }
+}
+
+/// Reads the selected fields into `BatterySnapshot`.
+/// Transactions are retried with the policy set by `BQ4050::set_retry_policy`
+#[derive(Copy, Clone)]
+pub struct SnapshotPoller {
+  pub fields: SnapshotFields,
+}
+
+impl SnapshotPoller {
+  pub const fn new(fields: SnapshotFields) -> Self {
+    SnapshotPoller { fields }
+  }
+
+  /// Polls every selected field, failed ones keep the previous value.
+  /// Returns `true` if all of them were read.
+  pub fn poll<I2C, E>(
+    &self,
+    bq4050: &mut BQ4050<I2C>,
+    snapshot: &mut BatterySnapshot<E>,
+    now: u32,
+  ) -> bool
+  where
+    I2C: WriteRead<Error = E> + Write<Error = E> + Read<Error = E>,
+  {
+    let mut poll = FieldPoll {
+      bq4050,
+      now,
+      ok: true,
+    };
+
+    for field in self.fields.iter() {
+      poll.field(field, snapshot);
+    }
+
+    poll.ok
+  }
+
+  /// Polls up to `count` selected fields, continuing after the last one read by the previous call.
+  /// Keeps every call short, the whole set is refreshed over several calls.
+  /// Returns `true` if all of the polled fields were read.
+  pub fn poll_next<I2C, E>(
+    &self,
+    bq4050: &mut BQ4050<I2C>,
+    snapshot: &mut BatterySnapshot<E>,
+    now: u32,
+    count: usize,
+  ) -> bool
+  where
+    I2C: WriteRead<Error = E> + Write<Error = E> + Read<Error = E>,
+  {
+    let mut poll = FieldPoll {
+      bq4050,
+      now,
+      ok: true,
+    };
+
+    let all = SnapshotField::ALL.len();
+    let start = snapshot.next_field % all;
+    let fields = (start..start + all)
+      .map(|i| i % all)
+      .filter(|&i| self.fields.contains(SnapshotField::ALL[i]))
+      .take(count);
+    for i in fields {
+      poll.field(SnapshotField::ALL[i], snapshot);
+      snapshot.next_field = i + 1;
+    }
+
+    poll.ok
+  }
+}
+
+// State of a single `SnapshotPoller::poll` or `SnapshotPoller::poll_next` call
+struct FieldPoll<'a, I2C: WriteRead> {
+  bq4050: &'a mut BQ4050<I2C>,
+  now: u32,
+  ok: bool,
+}
+
+impl<'a, I2C, E> FieldPoll<'a, I2C>
+where
+  I2C: WriteRead<Error = E> + Write<Error = E> + Read<Error = E>,
+{
+  fn field(&mut self, field: SnapshotField, snapshot: &mut BatterySnapshot<E>) {
+    match field {
+      SnapshotField::Voltage => self.read(&mut snapshot.voltage, |bq| bq.get_voltage()),
+      SnapshotField::Current => self.read(&mut snapshot.current, |bq| bq.get_current()),
+      SnapshotField::AverageCurrent => {
+        self.read(&mut snapshot.average_current, |bq| bq.get_average_current())
+      }
+      SnapshotField::StateOfCharge => self.read(&mut snapshot.state_of_charge, |bq| {
+        bq.get_relative_state_of_charge()
+      }),
+      SnapshotField::RemainingCapacity => self.read(&mut snapshot.remaining_capacity, |bq| {
+        bq.get_remaining_capacity()
+      }),
+      SnapshotField::FullChargeCapacity => self.read(&mut snapshot.full_charge_capacity, |bq| {
+        bq.get_full_charge_capacity()
+      }),
+      SnapshotField::CellVoltages => self.read(&mut snapshot.cell_voltages, |bq| {
+        Ok([
+          bq.get_cell_voltage_1()?,
+          bq.get_cell_voltage_2()?,
+          bq.get_cell_voltage_3()?,
+          bq.get_cell_voltage_4()?,
+        ])
+      }),
+      SnapshotField::Temperature => self.read(&mut snapshot.temperature, |bq| bq.get_temperature()),
+      SnapshotField::Temperatures => {
+        self.read(&mut snapshot.temperatures, |bq| bq.get_da_status2())
+      }
+      SnapshotField::TimeToEmpty => {
+        self.read(&mut snapshot.time_to_empty, |bq| bq.get_run_time_to_empty())
+      }
+      SnapshotField::TimeToFull => self.read(&mut snapshot.time_to_full, |bq| {
+        bq.get_average_time_to_full()
+      }),
+      SnapshotField::BatteryStatus => {
+        self.read(&mut snapshot.battery_status, |bq| bq.get_battery_status())
+      }
+      SnapshotField::OperationStatus => self.read(&mut snapshot.operation_status, |bq| {
+        bq.get_operation_status()
+      }),
+    }
+  }
+
+  fn read<T: Copy>(
+    &mut self,
+    reading: &mut Reading<T, E>,
+    op: impl FnOnce(&mut BQ4050<I2C>) -> Result<T, Error<E>>,
+  ) {
+    let result = op(self.bq4050);
+    self.ok &= reading.update(result, self.now);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bq4050::{Address, Cmd};
+  use crate::mock::{MockError, MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+
+  const POLLER: SnapshotPoller = SnapshotPoller::new(
+    SnapshotFields::NONE
+      .with(SnapshotField::Voltage)
+      .with(SnapshotField::Temperature)
+      .with(SnapshotField::BatteryStatus),
+  );
+
+  fn word(cmd: Cmd, value: u16) -> [Transaction; 2] {
+    [
+      Transaction::write(DEV, &[cmd as u8]),
+      Transaction::read(DEV, &value.to_le_bytes()),
+    ]
+  }
+
+  #[test]
+  fn poll_next_rotates() {
+    let script = [
+      word(Cmd::VoltageReg, 16_000),
+      word(Cmd::TemperatureReg, 2_981),
+      word(Cmd::BatteryStatusReg, 0x00C0),
+      word(Cmd::VoltageReg, 15_900),
+    ]
+    .concat();
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script));
+    let mut snapshot = BatterySnapshot::default();
+
+    assert!(POLLER.poll_next(&mut bq4050, &mut snapshot, 1, 2));
+    assert_eq!(snapshot.voltage.fresh(1, 0), Some(MilliVolts(16_000)));
+    assert_eq!(snapshot.temperature.fresh(1, 0), Some(DeciKelvin(2_981)));
+    assert_eq!(snapshot.battery_status.updated, None);
+
+    // Continues with the last selected field and wraps around
+    assert!(POLLER.poll_next(&mut bq4050, &mut snapshot, 2, 2));
+    assert_eq!(snapshot.battery_status.updated, Some(2));
+    assert_eq!(snapshot.voltage.fresh(2, 0), Some(MilliVolts(15_900)));
+    assert_eq!(snapshot.temperature.updated, Some(1));
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn errors_at_skips_older_errors() {
+    let script = [
+      [Transaction::Fail(MockError::Timeout)].as_slice(),
+      &word(Cmd::TemperatureReg, 2_981),
+    ]
+    .concat();
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script));
+    let mut snapshot = BatterySnapshot::default();
+
+    assert!(!POLLER.poll_next(&mut bq4050, &mut snapshot, 1, 1));
+    assert_eq!(snapshot.errors_at(1).count(), 1);
+
+    assert!(POLLER.poll_next(&mut bq4050, &mut snapshot, 2, 1));
+    assert_eq!(snapshot.errors_at(2).count(), 0);
+    // Voltage was not read again, its error is kept
+    assert_eq!(snapshot.errors().count(), 1);
+    assert_eq!(snapshot.voltage.value, None);
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn poll_reads_every_field() {
+    let script = [
+      word(Cmd::VoltageReg, 16_000),
+      word(Cmd::TemperatureReg, 2_981),
+      word(Cmd::BatteryStatusReg, 0x00C0),
+    ]
+    .concat();
+    let mut bq4050 = BQ4050::new(MockI2c::new(&script));
+    let mut snapshot = BatterySnapshot::default();
+
+    assert!(POLLER.poll(&mut bq4050, &mut snapshot, 1));
+    assert_eq!(snapshot.errors().count(), 0);
+    assert!(snapshot.battery_status.fresh(1, 0).is_some());
+    bq4050.release().done();
+  }
+}
@@ -0,0 +1,266 @@
+// 13.4 0x03 BatteryMode() bits
+const MODE_CAPM: u16 = 1 << 15;
+const MODE_CHGM: u16 = 1 << 14;
+const MODE_AM: u16 = 1 << 13;
+const MODE_PB: u16 = 1 << 9;
+const MODE_CC: u16 = 1 << 8;
+const MODE_CF: u16 = 1 << 7;
+const MODE_PBS: u16 = 1 << 1;
+const MODE_ICC: u16 = 1 << 0;
+
+// 13.23 0x16 BatteryStatus() bits
+const STATUS_OCA: u16 = 1 << 15;
+const STATUS_TCA: u16 = 1 << 14;
+const STATUS_OTA: u16 = 1 << 12;
+const STATUS_TDA: u16 = 1 << 11;
+const STATUS_RCA: u16 = 1 << 9;
+const STATUS_RTA: u16 = 1 << 8;
+const STATUS_INIT: u16 = 1 << 7;
+const STATUS_DSG: u16 = 1 << 6;
+const STATUS_FC: u16 = 1 << 5;
+const STATUS_FD: u16 = 1 << 4;
+const STATUS_EC_MASK: u16 = 0b1111;
+
+/// BatteryMode() flags
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatteryMode {
+  /// Capacity mode. `false` - capacity reported in mAh, `true` - in 10 mWh
+  pub capm: bool,
+  /// Charger mode. `true` disables ChargingVoltage() and ChargingCurrent() broadcasts to the host
+  pub chgm: bool,
+  /// Alarm mode. `true` disables AlarmWarning broadcasts to the host
+  pub am: bool,
+  /// Primary battery
+  pub pb: bool,
+  /// Internal charge controller enabled
+  pub cc: bool,
+  /// Condition flag. `true` requests a conditioning cycle
+  pub cf: bool,
+  /// Primary battery support
+  pub pbs: bool,
+  /// Internal charge controller supported
+  pub icc: bool,
+  /// Reserved bits, kept so that writing back the read value does not alter them
+  reserved: u16,
+}
+
+impl From<u16> for BatteryMode {
+  fn from(raw: u16) -> Self {
+    BatteryMode {
+      capm: raw & MODE_CAPM != 0,
+      chgm: raw & MODE_CHGM != 0,
+      am: raw & MODE_AM != 0,
+      pb: raw & MODE_PB != 0,
+      cc: raw & MODE_CC != 0,
+      cf: raw & MODE_CF != 0,
+      pbs: raw & MODE_PBS != 0,
+      icc: raw & MODE_ICC != 0,
+      reserved: raw
+        & !(MODE_CAPM | MODE_CHGM | MODE_AM | MODE_PB | MODE_CC | MODE_CF | MODE_PBS | MODE_ICC),
+    }
+  }
+}
+
+impl From<BatteryMode> for u16 {
+  fn from(mode: BatteryMode) -> Self {
+    let mut raw = mode.reserved;
+
+    for (set, bit) in [
+      (mode.capm, MODE_CAPM),
+      (mode.chgm, MODE_CHGM),
+      (mode.am, MODE_AM),
+      (mode.pb, MODE_PB),
+      (mode.cc, MODE_CC),
+      (mode.cf, MODE_CF),
+      (mode.pbs, MODE_PBS),
+      (mode.icc, MODE_ICC),
+    ] {
+      if set {
+        raw |= bit;
+      }
+    }
+
+    raw
+  }
+}
+
+/// Error code reported in BatteryStatus()[EC3:EC0]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+  Ok,
+  Busy,
+  ReservedCommand,
+  UnsupportedCommand,
+  AccessDenied,
+  OverflowUnderflow,
+  BadSize,
+  UnknownError,
+  Undefined(u8),
+}
+
+impl From<u8> for ErrorCode {
+  fn from(raw: u8) -> Self {
+    match raw {
+      0 => ErrorCode::Ok,
+      1 => ErrorCode::Busy,
+      2 => ErrorCode::ReservedCommand,
+      3 => ErrorCode::UnsupportedCommand,
+      4 => ErrorCode::AccessDenied,
+      5 => ErrorCode::OverflowUnderflow,
+      6 => ErrorCode::BadSize,
+      7 => ErrorCode::UnknownError,
+      other => ErrorCode::Undefined(other),
+    }
+  }
+}
+
+/// BatteryStatus() flags
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BatteryStatus {
+  /// Overcharged alarm
+  pub oca: bool,
+  /// Terminate charge alarm
+  pub tca: bool,
+  /// Overtemperature alarm
+  pub ota: bool,
+  /// Terminate discharge alarm
+  pub tda: bool,
+  /// Remaining capacity alarm
+  pub rca: bool,
+  /// Remaining time alarm
+  pub rta: bool,
+  /// Initialization complete
+  pub init: bool,
+  /// Discharging or relax mode
+  pub dsg: bool,
+  /// Fully charged
+  pub fc: bool,
+  /// Fully discharged
+  pub fd: bool,
+  /// Result of the last SBS command
+  pub error_code: ErrorCode,
+}
+
+impl BatteryStatus {
+  /// Any of the alarm flags is set
+  pub fn has_alarm(&self) -> bool {
+    self.oca || self.tca || self.ota || self.tda || self.rca || self.rta
+  }
+}
+
+impl From<u16> for BatteryStatus {
+  fn from(raw: u16) -> Self {
+    BatteryStatus {
+      oca: raw & STATUS_OCA != 0,
+      tca: raw & STATUS_TCA != 0,
+      ota: raw & STATUS_OTA != 0,
+      tda: raw & STATUS_TDA != 0,
+      rca: raw & STATUS_RCA != 0,
+      rta: raw & STATUS_RTA != 0,
+      init: raw & STATUS_INIT != 0,
+      dsg: raw & STATUS_DSG != 0,
+      fc: raw & STATUS_FC != 0,
+      fd: raw & STATUS_FD != 0,
+      error_code: ErrorCode::from((raw & STATUS_EC_MASK) as u8),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::bq4050::{Address, Cmd, BQ4050};
+  use crate::mock::{MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+
+  fn status(flags: [bool; 10], error_code: ErrorCode) -> BatteryStatus {
+    let [oca, tca, ota, tda, rca, rta, init, dsg, fc, fd] = flags;
+    BatteryStatus {
+      oca,
+      tca,
+      ota,
+      tda,
+      rca,
+      rta,
+      init,
+      dsg,
+      fc,
+      fd,
+      error_code,
+    }
+  }
+
+  #[test]
+  fn battery_status_decode() {
+    let t = true;
+    let f = false;
+    let cases = [
+      (
+        0x0000,
+        status([f, f, f, f, f, f, f, f, f, f], ErrorCode::Ok),
+      ),
+      // Initialized and discharging, the usual idle reading
+      (
+        0x00C0,
+        status([f, f, f, f, f, f, t, t, f, f], ErrorCode::Ok),
+      ),
+      // Charge terminated on full charge
+      (
+        0x40A0,
+        status([f, t, f, f, f, f, t, f, t, f], ErrorCode::Ok),
+      ),
+      (
+        0x0AD0,
+        status([f, f, f, t, t, f, t, t, f, t], ErrorCode::Ok),
+      ),
+      (
+        0x9100,
+        status([t, f, t, f, f, t, f, f, f, f], ErrorCode::Ok),
+      ),
+      (
+        0x00C4,
+        status([f, f, f, f, f, f, t, t, f, f], ErrorCode::AccessDenied),
+      ),
+      (
+        0x00C7,
+        status([f, f, f, f, f, f, t, t, f, f], ErrorCode::UnknownError),
+      ),
+      (
+        0x00CF,
+        status([f, f, f, f, f, f, t, t, f, f], ErrorCode::Undefined(15)),
+      ),
+    ];
+
+    for (raw, expected) in cases {
+      assert_eq!(BatteryStatus::from(raw), expected, "{:04x}", raw);
+    }
+    assert!(BatteryStatus::from(0x0800).has_alarm());
+    assert!(!BatteryStatus::from(0x00F7).has_alarm());
+  }
+
+  #[test]
+  fn battery_mode_decode() {
+    let mode = BatteryMode::from(0x6081);
+    assert!(!mode.capm && mode.chgm && mode.am && mode.cf && mode.icc);
+    assert!(!mode.pb && !mode.cc && !mode.pbs);
+
+    // Reserved bits survive the round trip
+    for raw in [0x0000, 0x6081, 0x8000, 0xFFFF, 0x1C7C] {
+      assert_eq!(u16::from(BatteryMode::from(raw)), raw, "{:04x}", raw);
+    }
+  }
+
+  #[test]
+  fn battery_status_read() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&[
+      Transaction::write(DEV, &[Cmd::BatteryStatusReg as u8]),
+      Transaction::read(DEV, &[0xC0, 0x00]),
+    ]));
+
+    let status = bq4050.get_battery_status().unwrap();
+    assert!(status.init && status.dsg);
+    assert_eq!(status.error_code, ErrorCode::Ok);
+    bq4050.release().done();
+  }
+}
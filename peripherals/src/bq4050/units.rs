@@ -0,0 +1,145 @@
+// Units of values reported by the gauge.
+// Wrapped into newtypes so that readings of different kinds can't be mixed up.
+
+/// Voltage in mV
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MilliVolts(pub u16);
+
+impl MilliVolts {
+  pub fn to_volts(self) -> f32 {
+    self.0 as f32 / 1000.0
+  }
+}
+
+/// Current in mA. Positive while charging, negative while discharging
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MilliAmps(pub i16);
+
+impl MilliAmps {
+  pub fn to_amps(self) -> f32 {
+    self.0 as f32 / 1000.0
+  }
+}
+
+/// Temperature in 0.1°K
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DeciKelvin(pub u16);
+
+impl DeciKelvin {
+  pub fn to_celsius(self) -> f32 {
+    self.0 as f32 / 10.0 - 273.15
+  }
+}
+
+/// Percentage
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Percent(pub u16);
+
+/// Time in minutes
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Minutes(pub u16);
+
+/// Power in cW
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CentiWatts(pub u16);
+
+/// Resistance in cΩ
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CentiOhms(pub u16);
+
+/// Unit of capacity registers, selected by BatteryMode()[CAPM]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CapacityUnit {
+  /// CAPM = 0
+  MilliAmpHours,
+  /// CAPM = 1
+  TenMilliWattHours,
+}
+
+/// Capacity in the unit the gauge currently reports in
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Capacity {
+  MilliAmpHours(u16),
+  TenMilliWattHours(u16),
+}
+
+impl Capacity {
+  pub fn new(unit: CapacityUnit, raw: u16) -> Self {
+    match unit {
+      CapacityUnit::MilliAmpHours => Capacity::MilliAmpHours(raw),
+      CapacityUnit::TenMilliWattHours => Capacity::TenMilliWattHours(raw),
+    }
+  }
+
+  pub fn unit(&self) -> CapacityUnit {
+    match self {
+      Capacity::MilliAmpHours(_) => CapacityUnit::MilliAmpHours,
+      Capacity::TenMilliWattHours(_) => CapacityUnit::TenMilliWattHours,
+    }
+  }
+
+  pub fn raw(&self) -> u16 {
+    match *self {
+      Capacity::MilliAmpHours(raw) | Capacity::TenMilliWattHours(raw) => raw,
+    }
+  }
+}
+
+/// Rate in the unit selected by BatteryMode()[CAPM]. Positive for charge, negative for discharge
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rate {
+  MilliAmps(i16),
+  TenMilliWatts(i16),
+}
+
+impl Rate {
+  pub fn new(unit: CapacityUnit, raw: i16) -> Self {
+    match unit {
+      CapacityUnit::MilliAmpHours => Rate::MilliAmps(raw),
+      CapacityUnit::TenMilliWattHours => Rate::TenMilliWatts(raw),
+    }
+  }
+
+  pub fn unit(&self) -> CapacityUnit {
+    match self {
+      Rate::MilliAmps(_) => CapacityUnit::MilliAmpHours,
+      Rate::TenMilliWatts(_) => CapacityUnit::TenMilliWattHours,
+    }
+  }
+
+  pub fn raw(&self) -> i16 {
+    match *self {
+      Rate::MilliAmps(raw) | Rate::TenMilliWatts(raw) => raw,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn unit_follows_capm() {
+    let cases = [
+      (
+        CapacityUnit::MilliAmpHours,
+        Capacity::MilliAmpHours(0x8000),
+        Rate::MilliAmps(-32768),
+      ),
+      (
+        CapacityUnit::TenMilliWattHours,
+        Capacity::TenMilliWattHours(0x8000),
+        Rate::TenMilliWatts(-32768),
+      ),
+    ];
+
+    for (unit, capacity, rate) in cases {
+      assert_eq!(Capacity::new(unit, 0x8000), capacity);
+      assert_eq!(capacity.unit(), unit);
+      assert_eq!(capacity.raw(), 0x8000);
+      assert_eq!(Rate::new(unit, -32768), rate);
+      assert_eq!(rate.unit(), unit);
+      assert_eq!(rate.raw(), -32768);
+    }
+  }
+}
@@ -0,0 +1,170 @@
+use core::fmt;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+use super::{Error, MacCmd, BQ4050};
+
+// Identification MAC subcommands, see 13.1 DeviceType() - StaticChemDFSignature().
+// FirmwareVersion() fields are sent most significant byte first, other replies are little endian.
+
+/// FirmwareVersion() reply
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FirmwareVersion {
+  pub device_number: u16,
+  pub version: u16,
+  pub build: u16,
+  pub firmware_type: u8,
+  /// Impedance Track version
+  pub it_version: u16,
+}
+
+impl FirmwareVersion {
+  fn from_block(data: &[u8]) -> Self {
+    FirmwareVersion {
+      device_number: BigEndian::read_u16(&data[0..2]),
+      version: BigEndian::read_u16(&data[2..4]),
+      build: BigEndian::read_u16(&data[4..6]),
+      firmware_type: data[6],
+      it_version: BigEndian::read_u16(&data[7..9]),
+    }
+  }
+}
+
+// Formats as "4050 v2.09 build 23", version and build are BCD
+impl fmt::Display for FirmwareVersion {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{:04x} v{:x}.{:02x} build {:x}",
+      self.device_number,
+      self.version >> 8,
+      self.version & 0xFF,
+      self.build
+    )
+  }
+}
+
+/// Gauge identification, ties firmware and chemistry profile to a pack
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GaugeVersion {
+  pub device_type: u16,
+  pub firmware: FirmwareVersion,
+  pub hardware_version: u16,
+  /// Instruction flash checksum
+  pub if_checksum: u32,
+  /// Signature of static data flash
+  pub static_df_signature: u16,
+  /// Chemistry profile
+  pub chem_id: u16,
+  /// Signature of static chemistry data flash
+  pub static_chem_df_signature: u16,
+}
+
+impl<I2C, I2cError> BQ4050<I2C>
+where
+  I2C: WriteRead<Error = I2cError> + Write<Error = I2cError> + Read<Error = I2cError>,
+{
+  pub fn get_device_type(&mut self) -> Result<u16, Error<I2cError>> {
+    self.mac_read_u16(MacCmd::DeviceType)
+  }
+
+  pub fn get_firmware_version(&mut self) -> Result<FirmwareVersion, Error<I2cError>> {
+    let mut buffer = [0u8; 11];
+    let len = self.mac_read(MacCmd::FirmwareVersion, &mut buffer)?;
+    if len < 9 {
+      return Err(Error::InvalidLength(len as u8));
+    }
+
+    Ok(FirmwareVersion::from_block(&buffer))
+  }
+
+  pub fn get_hardware_version(&mut self) -> Result<u16, Error<I2cError>> {
+    self.mac_read_u16(MacCmd::HardwareVersion)
+  }
+
+  pub fn get_if_checksum(&mut self) -> Result<u32, Error<I2cError>> {
+    let mut buffer = [0u8; 4];
+    let len = self.mac_read(MacCmd::IfChecksum, &mut buffer)?;
+    if len < buffer.len() {
+      return Err(Error::InvalidLength(len as u8));
+    }
+
+    Ok(LittleEndian::read_u32(&buffer))
+  }
+
+  pub fn get_static_df_signature(&mut self) -> Result<u16, Error<I2cError>> {
+    self.mac_read_u16(MacCmd::StaticDfSignature)
+  }
+
+  pub fn get_chem_id(&mut self) -> Result<u16, Error<I2cError>> {
+    self.mac_read_u16(MacCmd::ChemId)
+  }
+
+  pub fn get_static_chem_df_signature(&mut self) -> Result<u16, Error<I2cError>> {
+    self.mac_read_u16(MacCmd::StaticChemDfSignature)
+  }
+
+  /// Reads all identification subcommands
+  pub fn get_gauge_version(&mut self) -> Result<GaugeVersion, Error<I2cError>> {
+    Ok(GaugeVersion {
+      device_type: self.get_device_type()?,
+      firmware: self.get_firmware_version()?,
+      hardware_version: self.get_hardware_version()?,
+      if_checksum: self.get_if_checksum()?,
+      static_df_signature: self.get_static_df_signature()?,
+      chem_id: self.get_chem_id()?,
+      static_chem_df_signature: self.get_static_chem_df_signature()?,
+    })
+  }
+
+  fn mac_read_u16(&mut self, cmd: MacCmd) -> Result<u16, Error<I2cError>> {
+    let mut buffer = [0u8; 2];
+    let len = self.mac_read(cmd, &mut buffer)?;
+    if len < buffer.len() {
+      return Err(Error::InvalidLength(len as u8));
+    }
+
+    Ok(LittleEndian::read_u16(&buffer))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::bq4050::{Address, Error, BQ4050};
+  use crate::mock::{MockI2c, Transaction};
+
+  const DEV: u8 = Address::Dev as u8;
+  const MAC: u8 = Address::Mac as u8;
+
+  fn if_checksum_script(reply: &[u8]) -> [Transaction; 3] {
+    [
+      Transaction::write(DEV, &[MAC, 2, 0x04, 0x00]),
+      Transaction::write(DEV, &[MAC]),
+      Transaction::read(DEV, reply),
+    ]
+  }
+
+  #[test]
+  fn if_checksum() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&if_checksum_script(&[
+      6, 0x04, 0x00, 0x78, 0x56, 0x34, 0x12,
+    ])));
+
+    assert_eq!(bq4050.get_if_checksum().unwrap(), 0x1234_5678);
+    bq4050.release().done();
+  }
+
+  #[test]
+  fn if_checksum_too_short() {
+    let mut bq4050 = BQ4050::new(MockI2c::new(&if_checksum_script(&[
+      4, 0x04, 0x00, 0x78, 0x56,
+    ])));
+
+    assert!(matches!(
+      bq4050.get_if_checksum(),
+      Err(Error::InvalidLength(2))
+    ));
+    bq4050.release().done();
+  }
+}
@@ -0,0 +1,348 @@
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+// Stuck bus recovery, see I2C specification (UM10204) 3.1.16 Bus clear.
+// A slave reset or glitched in the middle of a byte keeps SDA low while waiting for the rest of the clocks.
+// Up to nine SCL pulses let it shift the byte out, then a STOP condition returns it to the idle state.
+// Pins are expected to be open drain outputs that can be read back, e.g. I2C pins taken from the peripheral.
+
+const RECOVERY_CLOCKS: u8 = 9;
+// Upper bound of bus errors ignored after a failed recovery
+const MAX_HOLDOFF: u8 = 64;
+// Half of SCL period at 100 kHz
+const HALF_PERIOD_US: u32 = 5;
+
+/// Tracks consecutive bus errors and clears the bus once they pile up.
+/// A failed recovery is retried later, after twice as many bus errors as the previous wait.
+pub struct BusRecovery {
+  threshold: u8,
+  failures: u8,
+  recoveries: u32,
+  stuck: u32,
+  // Bus errors left to ignore before the next recovery
+  holdoff: u8,
+  // Next holdoff, doubled by every failed recovery in a row
+  next_holdoff: u8,
+}
+
+impl BusRecovery {
+  /// `threshold` - amount of consecutive bus errors triggering recovery
+  pub const fn new(threshold: u8) -> Self {
+    BusRecovery {
+      threshold,
+      failures: 0,
+      recoveries: 0,
+      stuck: 0,
+      holdoff: 0,
+      next_holdoff: 1,
+    }
+  }
+
+  /// Records outcome of a transaction or a polling cycle.
+  /// `bus_error` is true for timeouts and arbitration loss, NACKs are not bus errors.
+  /// Returns true when the bus should be recovered.
+  pub fn record(&mut self, bus_error: bool) -> bool {
+    if !bus_error {
+      self.failures = 0;
+      self.holdoff = 0;
+      self.next_holdoff = 1;
+      return false;
+    }
+
+    if self.holdoff > 0 {
+      self.holdoff -= 1;
+      return false;
+    }
+
+    self.failures = self.failures.saturating_add(1);
+    self.failures >= self.threshold
+  }
+
+  /// Clocks SCL until SDA is released, up to nine times, and issues STOP.
+  /// Returns true if SDA is high afterwards. The bus peripheral has to be re-initialized by the caller.
+  /// SDA still low or a pin error counts as a stuck bus and delays the next recovery.
+  pub fn recover<SCL, SDA, E>(
+    &mut self,
+    scl: &mut SCL,
+    sda: &mut SDA,
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<bool, E>
+  where
+    SCL: OutputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+  {
+    self.failures = 0;
+    self.recoveries = self.recoveries.wrapping_add(1);
+
+    let result = Self::clear(scl, sda, delay_source);
+    if !matches!(result, Ok(true)) {
+      self.stuck = self.stuck.wrapping_add(1);
+      self.holdoff = self.next_holdoff;
+      self.next_holdoff = self.next_holdoff.saturating_mul(2).min(MAX_HOLDOFF);
+    } else {
+      self.next_holdoff = 1;
+    }
+    result
+  }
+
+  fn clear<SCL, SDA, E>(
+    scl: &mut SCL,
+    sda: &mut SDA,
+    delay_source: &mut impl DelayUs<u32>,
+  ) -> Result<bool, E>
+  where
+    SCL: OutputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+  {
+    sda.set_high()?;
+    scl.set_high()?;
+    delay_source.delay_us(HALF_PERIOD_US);
+
+    for _ in 0..RECOVERY_CLOCKS {
+      if sda.is_high()? {
+        break;
+      }
+
+      scl.set_low()?;
+      delay_source.delay_us(HALF_PERIOD_US);
+      scl.set_high()?;
+      delay_source.delay_us(HALF_PERIOD_US);
+    }
+
+    // STOP: SDA goes high while SCL is high
+    scl.set_low()?;
+    delay_source.delay_us(HALF_PERIOD_US);
+    sda.set_low()?;
+    delay_source.delay_us(HALF_PERIOD_US);
+    scl.set_high()?;
+    delay_source.delay_us(HALF_PERIOD_US);
+    sda.set_high()?;
+    delay_source.delay_us(HALF_PERIOD_US);
+
+    sda.is_high()
+  }
+
+  /// Amount of recoveries performed
+  pub fn recoveries(&self) -> u32 {
+    self.recoveries
+  }
+
+  /// Amount of recoveries that left SDA low or failed on a pin
+  pub fn stuck(&self) -> u32 {
+    self.stuck
+  }
+
+  /// Amount of consecutive bus errors recorded since the last success
+  pub fn failures(&self) -> u8 {
+    self.failures
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mock::MockDelay;
+  use core::cell::RefCell;
+  use std::rc::Rc;
+  use std::vec::Vec;
+
+  // Open drain bus with a slave holding SDA low for `hold_clocks` SCL pulses
+  #[derive(Default)]
+  struct Bus {
+    scl: bool,
+    sda: bool,
+    hold_clocks: u8,
+    pulses: u8,
+    // Pins fail to switch
+    broken: bool,
+    // (SCL, SDA) line levels after every pin change
+    trace: Vec<(bool, bool)>,
+  }
+
+  impl Bus {
+    fn sda_line(&self) -> bool {
+      self.sda && self.pulses >= self.hold_clocks
+    }
+
+    fn log(&mut self) {
+      let levels = (self.scl, self.sda_line());
+      self.trace.push(levels);
+    }
+  }
+
+  struct Scl(Rc<RefCell<Bus>>);
+  struct Sda(Rc<RefCell<Bus>>);
+
+  impl OutputPin for Scl {
+    type Error = ();
+
+    fn set_low(&mut self) -> Result<(), ()> {
+      let mut bus = self.0.borrow_mut();
+      if bus.broken {
+        return Err(());
+      }
+      bus.scl = false;
+      bus.log();
+      Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), ()> {
+      let mut bus = self.0.borrow_mut();
+      if bus.broken {
+        return Err(());
+      }
+      if !bus.scl && bus.pulses < u8::MAX {
+        bus.pulses += 1;
+      }
+      bus.scl = true;
+      bus.log();
+      Ok(())
+    }
+  }
+
+  impl OutputPin for Sda {
+    type Error = ();
+
+    fn set_low(&mut self) -> Result<(), ()> {
+      let mut bus = self.0.borrow_mut();
+      bus.sda = false;
+      bus.log();
+      Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), ()> {
+      let mut bus = self.0.borrow_mut();
+      if bus.broken {
+        return Err(());
+      }
+      bus.sda = true;
+      bus.log();
+      Ok(())
+    }
+  }
+
+  impl InputPin for Sda {
+    type Error = ();
+
+    fn is_high(&self) -> Result<bool, ()> {
+      Ok(self.0.borrow().sda_line())
+    }
+
+    fn is_low(&self) -> Result<bool, ()> {
+      Ok(!self.0.borrow().sda_line())
+    }
+  }
+
+  fn recover_bus(recovery: &mut BusRecovery, bus: Bus) -> (Result<bool, ()>, Bus) {
+    let bus = Rc::new(RefCell::new(bus));
+    let mut scl = Scl(bus.clone());
+    let mut sda = Sda(bus.clone());
+
+    let result = recovery.recover(&mut scl, &mut sda, &mut MockDelay::default());
+    drop((scl, sda));
+    (result, Rc::try_unwrap(bus).ok().unwrap().into_inner())
+  }
+
+  // Returns SDA state after recovery and the bus
+  fn recover(recovery: &mut BusRecovery, hold_clocks: u8) -> (bool, Bus) {
+    let bus = Bus {
+      scl: true,
+      hold_clocks,
+      ..Bus::default()
+    };
+    let (result, bus) = recover_bus(recovery, bus);
+    (result.unwrap(), bus)
+  }
+
+  #[test]
+  fn sda_released_after_pulses() {
+    let mut recovery = BusRecovery::new(3);
+    let (released, bus) = recover(&mut recovery, 4);
+
+    assert!(released);
+    // Four clocks freeing SDA and one more for the STOP
+    assert_eq!(bus.pulses, 5);
+    assert_eq!(recovery.recoveries(), 1);
+    assert_eq!(recovery.stuck(), 0);
+  }
+
+  #[test]
+  fn sda_stuck_after_nine_pulses() {
+    let mut recovery = BusRecovery::new(3);
+    let (released, bus) = recover(&mut recovery, u8::MAX);
+
+    assert!(!released);
+    assert_eq!(bus.pulses, RECOVERY_CLOCKS + 1);
+    assert_eq!(recovery.recoveries(), 1);
+    assert_eq!(recovery.stuck(), 1);
+  }
+
+  #[test]
+  fn stop_generated() {
+    let mut recovery = BusRecovery::new(3);
+    let (_, bus) = recover(&mut recovery, 0);
+
+    // SDA falls and then rises while SCL stays high
+    let stop = [(true, false), (true, true)];
+    assert!(bus.trace.ends_with(&stop));
+    assert!(bus.trace[..bus.trace.len() - 2]
+      .last()
+      .is_some_and(|&(scl, sda)| !scl && !sda));
+  }
+
+  #[test]
+  fn record_threshold() {
+    let mut recovery = BusRecovery::new(3);
+
+    assert!(!recovery.record(true));
+    assert!(!recovery.record(true));
+    // Success in between starts over
+    assert!(!recovery.record(false));
+    assert_eq!(recovery.failures(), 0);
+
+    assert!(!recovery.record(true));
+    assert!(!recovery.record(true));
+    assert!(recovery.record(true));
+    assert_eq!(recovery.failures(), 3);
+
+    recover(&mut recovery, 0);
+    assert_eq!(recovery.failures(), 0);
+    assert!(!recovery.record(true));
+  }
+
+  #[test]
+  fn failed_recovery_is_retried_later() {
+    let mut recovery = BusRecovery::new(1);
+
+    recover(&mut recovery, u8::MAX);
+    // One bus error is ignored after the first failed recovery
+    assert!(!recovery.record(true));
+    assert!(recovery.record(true));
+
+    recover(&mut recovery, u8::MAX);
+    assert!(!recovery.record(true));
+    assert!(!recovery.record(true));
+    assert!(recovery.record(true));
+    assert_eq!(recovery.stuck(), 2);
+
+    // Released bus starts over
+    recover(&mut recovery, 0);
+    assert!(!recovery.record(false));
+    assert!(recovery.record(true));
+  }
+
+  #[test]
+  fn pin_error_counted_as_stuck() {
+    let mut recovery = BusRecovery::new(1);
+    let bus = Bus {
+      broken: true,
+      ..Bus::default()
+    };
+
+    let (result, _) = recover_bus(&mut recovery, bus);
+    assert_eq!(result, Err(()));
+    assert_eq!(recovery.stuck(), 1);
+    assert!(!recovery.record(true));
+    assert!(recovery.record(true));
+  }
+}
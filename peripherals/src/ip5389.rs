@@ -0,0 +1,398 @@
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+/// I2C address
+pub const ADDRESS: u8 = 0x75;
+
+// Registers of the IP5389 power path controller.
+// UNVERIFIED: addresses, bit positions and ADC scaling were not checked against the IP5389 datasheet.
+// Confirm them before enabling the driver on hardware, see TODO.md.
+// Multi-byte ADC values are split into low/high registers and must be read byte by byte,
+// the chip does not auto-increment the register pointer.
+#[derive(Copy, Clone)]
+pub enum Reg {
+  SysCtl0 = 0x00,
+  PortCtl = 0x01,
+  VbusAdcLow = 0x50,
+  VbusAdcHigh = 0x51,
+  VsysAdcLow = 0x52,
+  VsysAdcHigh = 0x53,
+  ChargeState = 0xD0,
+  SysState = 0xD1,
+  PortState = 0xD2,
+  FastChargeState = 0xD3,
+  FaultState = 0xD4,
+}
+
+// SYS_CTL0 bits
+const SYS_CTL0_CHARGER_EN: u8 = 1 << 0;
+const SYS_CTL0_BOOST_EN: u8 = 1 << 1;
+
+// CHARGE_STATE bits
+const CHARGE_STATE_CHARGING: u8 = 1 << 3;
+const CHARGE_STATE_STAGE_MASK: u8 = 0b0000_0111;
+
+// SYS_STATE bits
+const SYS_STATE_DISCHARGING: u8 = 1 << 0;
+
+// FAST_CHARGE_STATE bits
+const FAST_CHARGE_PROTOCOL_MASK: u8 = 0b0000_1111;
+const FAST_CHARGE_SINK: u8 = 1 << 4;
+
+// FAULT_STATE bits
+const FAULT_VBUS_OVER_VOLTAGE: u8 = 1 << 0;
+const FAULT_OUTPUT_OVER_CURRENT: u8 = 1 << 1;
+const FAULT_OUTPUT_SHORT: u8 = 1 << 2;
+const FAULT_OVER_TEMPERATURE: u8 = 1 << 3;
+const FAULT_BATTERY_UNDER_VOLTAGE: u8 = 1 << 4;
+
+/// Power port of the powerbank
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Port {
+  UsbC,
+  UsbA,
+  Dc,
+}
+
+impl Port {
+  // Same bit position is used in PORT_CTL (enable) and PORT_STATE (attached).
+  // Active flag of the port lives 4 bits higher in PORT_STATE.
+  fn bit(self) -> u8 {
+    match self {
+      Port::UsbC => 1 << 0,
+      Port::UsbA => 1 << 1,
+      Port::Dc => 1 << 2,
+    }
+  }
+}
+
+/// Stage of the battery charger
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChargeStage {
+  Idle,
+  Trickle,
+  ConstantCurrent,
+  ConstantVoltage,
+  Full,
+  Timeout,
+  Unknown(u8),
+}
+
+impl From<u8> for ChargeStage {
+  fn from(raw: u8) -> Self {
+    match raw {
+      0 => ChargeStage::Idle,
+      1 => ChargeStage::Trickle,
+      2 => ChargeStage::ConstantCurrent,
+      3 => ChargeStage::ConstantVoltage,
+      4 => ChargeStage::Full,
+      5 => ChargeStage::Timeout,
+      other => ChargeStage::Unknown(other),
+    }
+  }
+}
+
+/// Charge and discharge state of the battery
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PowerState {
+  pub charging: bool,
+  pub discharging: bool,
+  pub stage: ChargeStage,
+}
+
+/// Status of the single port
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PortStatus {
+  /// Port is allowed to operate
+  pub enabled: bool,
+  /// Something is plugged in
+  pub attached: bool,
+  /// Power is flowing through the port
+  pub active: bool,
+}
+
+/// Negotiated fast charge protocol
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Protocol {
+  None,
+  Qc2,
+  Qc3,
+  Fcp,
+  Scp,
+  Afc,
+  Pd,
+  Pps,
+  Unknown(u8),
+}
+
+impl From<u8> for Protocol {
+  fn from(raw: u8) -> Self {
+    match raw {
+      0 => Protocol::None,
+      1 => Protocol::Qc2,
+      2 => Protocol::Qc3,
+      3 => Protocol::Fcp,
+      4 => Protocol::Scp,
+      5 => Protocol::Afc,
+      6 => Protocol::Pd,
+      7 => Protocol::Pps,
+      other => Protocol::Unknown(other),
+    }
+  }
+}
+
+/// Fast charge negotiation state
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FastCharge {
+  pub protocol: Protocol,
+  /// `true` if powerbank is the sink (being charged), `false` if it is the source
+  pub sink: bool,
+}
+
+/// Fault flags
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Faults {
+  pub vbus_over_voltage: bool,
+  pub output_over_current: bool,
+  pub output_short: bool,
+  pub over_temperature: bool,
+  pub battery_under_voltage: bool,
+}
+
+impl Faults {
+  pub fn any(&self) -> bool {
+    self.vbus_over_voltage
+      || self.output_over_current
+      || self.output_short
+      || self.over_temperature
+      || self.battery_under_voltage
+  }
+}
+
+impl From<u8> for Faults {
+  fn from(raw: u8) -> Self {
+    Faults {
+      vbus_over_voltage: raw & FAULT_VBUS_OVER_VOLTAGE != 0,
+      output_over_current: raw & FAULT_OUTPUT_OVER_CURRENT != 0,
+      output_short: raw & FAULT_OUTPUT_SHORT != 0,
+      over_temperature: raw & FAULT_OVER_TEMPERATURE != 0,
+      battery_under_voltage: raw & FAULT_BATTERY_UNDER_VOLTAGE != 0,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Error<I2cError> {
+  I2cError(I2cError),
+}
+
+pub struct IP5389<I2C> {
+  i2c: I2C,
+}
+
+impl<I2C, I2cError> IP5389<I2C>
+where
+  I2C: WriteRead<Error = I2cError> + Write<Error = I2cError>,
+{
+  pub fn new(i2c: I2C) -> IP5389<I2C> {
+    IP5389 { i2c }
+  }
+
+  pub fn release(self) -> I2C {
+    self.i2c
+  }
+
+  // Charger and boost
+
+  pub fn get_power_state(&mut self) -> Result<PowerState, Error<I2cError>> {
+    let charge = self.read_reg(Reg::ChargeState)?;
+    let sys = self.read_reg(Reg::SysState)?;
+
+    Ok(PowerState {
+      charging: charge & CHARGE_STATE_CHARGING != 0,
+      discharging: sys & SYS_STATE_DISCHARGING != 0,
+      stage: ChargeStage::from(charge & CHARGE_STATE_STAGE_MASK),
+    })
+  }
+
+  pub fn set_charger_enabled(&mut self, enabled: bool) -> Result<(), Error<I2cError>> {
+    self.update_reg(Reg::SysCtl0, SYS_CTL0_CHARGER_EN, enabled)
+  }
+
+  pub fn set_boost_enabled(&mut self, enabled: bool) -> Result<(), Error<I2cError>> {
+    self.update_reg(Reg::SysCtl0, SYS_CTL0_BOOST_EN, enabled)
+  }
+
+  // ADC
+  // Raw ADC counts. Scaling to millivolts waits for the LSB size from the datasheet, see the register map note.
+
+  pub fn get_vbus_adc(&mut self) -> Result<u16, Error<I2cError>> {
+    self.read_reg16(Reg::VbusAdcLow, Reg::VbusAdcHigh)
+  }
+
+  pub fn get_vsys_adc(&mut self) -> Result<u16, Error<I2cError>> {
+    self.read_reg16(Reg::VsysAdcLow, Reg::VsysAdcHigh)
+  }
+
+  // Ports
+
+  pub fn set_port_enabled(&mut self, port: Port, enabled: bool) -> Result<(), Error<I2cError>> {
+    self.update_reg(Reg::PortCtl, port.bit(), enabled)
+  }
+
+  pub fn get_port_status(&mut self, port: Port) -> Result<PortStatus, Error<I2cError>> {
+    let ctl = self.read_reg(Reg::PortCtl)?;
+    let state = self.read_reg(Reg::PortState)?;
+
+    Ok(PortStatus {
+      enabled: ctl & port.bit() != 0,
+      attached: state & port.bit() != 0,
+      active: state & (port.bit() << 4) != 0,
+    })
+  }
+
+  // Fast charge
+
+  pub fn get_fast_charge(&mut self) -> Result<FastCharge, Error<I2cError>> {
+    let raw = self.read_reg(Reg::FastChargeState)?;
+
+    Ok(FastCharge {
+      protocol: Protocol::from(raw & FAST_CHARGE_PROTOCOL_MASK),
+      sink: raw & FAST_CHARGE_SINK != 0,
+    })
+  }
+
+  // Faults
+
+  pub fn get_faults(&mut self) -> Result<Faults, Error<I2cError>> {
+    Ok(Faults::from(self.read_reg(Reg::FaultState)?))
+  }
+
+  fn read_reg(&mut self, reg: Reg) -> Result<u8, Error<I2cError>> {
+    let mut buffer = [0u8; 1];
+    self.i2c.write_read(ADDRESS, &[reg as u8], &mut buffer)?;
+    Ok(buffer[0])
+  }
+
+  fn read_reg16(&mut self, low: Reg, high: Reg) -> Result<u16, Error<I2cError>> {
+    let low = self.read_reg(low)?;
+    let high = self.read_reg(high)?;
+    Ok(u16::from_le_bytes([low, high]))
+  }
+
+  fn write_reg(&mut self, reg: Reg, value: u8) -> Result<(), Error<I2cError>> {
+    self.i2c.write(ADDRESS, &[reg as u8, value])?;
+    Ok(())
+  }
+
+  fn update_reg(&mut self, reg: Reg, mask: u8, set: bool) -> Result<(), Error<I2cError>> {
+    let value = self.read_reg(reg)?;
+    let value = if set { value | mask } else { value & !mask };
+    self.write_reg(reg, value)
+  }
+}
+
+impl<E> From<E> for Error<E> {
+  fn from(error: E) -> Self {
+    Error::I2cError(error)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mock::{MockError, MockI2c, Transaction};
+
+  #[test]
+  fn adc_is_read_low_byte_first() {
+    let mut ip5389 = IP5389::new(MockI2c::new(&[
+      Transaction::write_read(ADDRESS, &[0x50], &[0x34]),
+      Transaction::write_read(ADDRESS, &[0x51], &[0x12]),
+    ]));
+
+    assert_eq!(ip5389.get_vbus_adc().unwrap(), 0x1234);
+    ip5389.release().done();
+  }
+
+  #[test]
+  fn update_keeps_other_bits() {
+    let mut ip5389 = IP5389::new(MockI2c::new(&[
+      Transaction::write_read(ADDRESS, &[0x00], &[0b1000_0001]),
+      Transaction::write(ADDRESS, &[0x00, 0b1000_0011]),
+      Transaction::write_read(ADDRESS, &[0x00], &[0b1000_0011]),
+      Transaction::write(ADDRESS, &[0x00, 0b1000_0010]),
+    ]));
+
+    ip5389.set_boost_enabled(true).unwrap();
+    ip5389.set_charger_enabled(false).unwrap();
+    ip5389.release().done();
+  }
+
+  #[test]
+  fn power_state() {
+    let mut ip5389 = IP5389::new(MockI2c::new(&[
+      Transaction::write_read(ADDRESS, &[0xD0], &[CHARGE_STATE_CHARGING | 2]),
+      Transaction::write_read(ADDRESS, &[0xD1], &[0]),
+    ]));
+
+    assert_eq!(
+      ip5389.get_power_state().unwrap(),
+      PowerState {
+        charging: true,
+        discharging: false,
+        stage: ChargeStage::ConstantCurrent,
+      }
+    );
+    ip5389.release().done();
+  }
+
+  #[test]
+  fn port_status() {
+    let mut ip5389 = IP5389::new(MockI2c::new(&[
+      Transaction::write_read(ADDRESS, &[0x01], &[0b0000_0010]),
+      Transaction::write_read(ADDRESS, &[0xD2], &[0b0010_0010]),
+    ]));
+
+    assert_eq!(
+      ip5389.get_port_status(Port::UsbA).unwrap(),
+      PortStatus {
+        enabled: true,
+        attached: true,
+        active: true,
+      }
+    );
+    ip5389.release().done();
+  }
+
+  #[test]
+  fn fast_charge_and_faults() {
+    let mut ip5389 = IP5389::new(MockI2c::new(&[
+      Transaction::write_read(ADDRESS, &[0xD3], &[FAST_CHARGE_SINK | 6]),
+      Transaction::write_read(ADDRESS, &[0xD4], &[FAULT_OUTPUT_SHORT]),
+    ]));
+
+    assert_eq!(
+      ip5389.get_fast_charge().unwrap(),
+      FastCharge {
+        protocol: Protocol::Pd,
+        sink: true,
+      }
+    );
+    let faults = ip5389.get_faults().unwrap();
+    assert!(faults.output_short && faults.any());
+    ip5389.release().done();
+  }
+
+  #[test]
+  fn bus_error_is_reported() {
+    let mut ip5389 = IP5389::new(MockI2c::new(&[
+      Transaction::write_read(ADDRESS, &[0x52], &[0x00]),
+      Transaction::Fail(MockError::Nack),
+    ]));
+
+    assert!(matches!(
+      ip5389.get_vsys_adc(),
+      Err(Error::I2cError(MockError::Nack))
+    ));
+    ip5389.release().done();
+  }
+}
@@ -1,7 +1,20 @@
 #![no_std]
 
+#[cfg(test)]
+extern crate std;
+
 #[allow(dead_code)]
 pub mod display;
 
 #[allow(dead_code)]
 pub mod bq4050;
+
+#[allow(dead_code)]
+pub mod ip5389;
+
+#[allow(dead_code)]
+pub mod i2c_recovery;
+
+#[cfg(test)]
+#[allow(dead_code)]
+mod mock;
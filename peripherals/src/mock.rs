@@ -0,0 +1,196 @@
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+// Scripted I2C bus for unit tests.
+// Every driver transaction is matched against the next expected one, replies shorter than
+// the read buffer are padded with 0xFF like an idle bus would do.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MockError {
+  Nack,
+  Timeout,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Transaction {
+  WriteRead(u8, Vec<u8>, Vec<u8>),
+  Write(u8, Vec<u8>),
+  Read(u8, Vec<u8>),
+  /// Next transaction of any kind fails
+  Fail(MockError),
+}
+
+impl Transaction {
+  pub fn write_read(address: u8, bytes: &[u8], reply: &[u8]) -> Self {
+    Transaction::WriteRead(address, bytes.to_vec(), reply.to_vec())
+  }
+
+  pub fn write(address: u8, bytes: &[u8]) -> Self {
+    Transaction::Write(address, bytes.to_vec())
+  }
+
+  pub fn read(address: u8, reply: &[u8]) -> Self {
+    Transaction::Read(address, reply.to_vec())
+  }
+}
+
+pub struct MockI2c {
+  script: VecDeque<Transaction>,
+}
+
+impl MockI2c {
+  pub fn new(script: &[Transaction]) -> Self {
+    MockI2c {
+      script: script.iter().cloned().collect(),
+    }
+  }
+
+  /// Panics if some of the expected transactions were not made
+  pub fn done(&self) {
+    assert!(
+      self.script.is_empty(),
+      "unused transactions: {:?}",
+      self.script
+    );
+  }
+
+  fn next(&mut self) -> Result<Transaction, MockError> {
+    match self.script.pop_front() {
+      Some(Transaction::Fail(error)) => Err(error),
+      Some(transaction) => Ok(transaction),
+      None => panic!("unexpected transaction"),
+    }
+  }
+}
+
+fn fill(buffer: &mut [u8], reply: &[u8]) {
+  for (i, byte) in buffer.iter_mut().enumerate() {
+    *byte = reply.get(i).copied().unwrap_or(0xFF);
+  }
+}
+
+impl WriteRead for MockI2c {
+  type Error = MockError;
+
+  fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), MockError> {
+    match self.next()? {
+      Transaction::WriteRead(expected, write, reply) => {
+        assert_eq!((address, bytes), (expected, write.as_slice()));
+        fill(buffer, &reply);
+        Ok(())
+      }
+      other => panic!("expected {:?}, got write_read {:02x?}", other, bytes),
+    }
+  }
+}
+
+impl Write for MockI2c {
+  type Error = MockError;
+
+  fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), MockError> {
+    match self.next()? {
+      Transaction::Write(expected, write) => {
+        assert_eq!((address, bytes), (expected, write.as_slice()));
+        Ok(())
+      }
+      other => panic!("expected {:?}, got write {:02x?}", other, bytes),
+    }
+  }
+}
+
+impl Read for MockI2c {
+  type Error = MockError;
+
+  fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), MockError> {
+    match self.next()? {
+      Transaction::Read(expected, reply) => {
+        assert_eq!(address, expected);
+        fill(buffer, &reply);
+        Ok(())
+      }
+      other => panic!("expected {:?}, got read", other),
+    }
+  }
+}
+
+/// Delay that only sums up the requested time
+#[derive(Default)]
+pub struct MockDelay {
+  pub total_us: u32,
+}
+
+impl DelayUs<u32> for MockDelay {
+  fn delay_us(&mut self, us: u32) {
+    self.total_us += us;
+  }
+}
+
+#[cfg(feature = "async")]
+mod asynch {
+  use core::future::Future;
+  use core::pin::pin;
+  use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+  use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+  use embedded_hal_async::i2c::{self, ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+
+  use super::{MockError, MockI2c};
+
+  impl i2c::Error for MockError {
+    fn kind(&self) -> ErrorKind {
+      match self {
+        MockError::Nack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+        MockError::Timeout => ErrorKind::Other,
+      }
+    }
+  }
+
+  impl ErrorType for MockI2c {
+    type Error = MockError;
+  }
+
+  // Same script as the blocking bus, operations are matched as a whole transaction
+  impl I2c for MockI2c {
+    async fn transaction(
+      &mut self,
+      address: u8,
+      operations: &mut [Operation<'_>],
+    ) -> Result<(), MockError> {
+      match operations {
+        [Operation::Write(bytes), Operation::Read(buffer)] => {
+          WriteRead::write_read(self, address, bytes, buffer)
+        }
+        [Operation::Write(bytes)] => Write::write(self, address, bytes),
+        [Operation::Read(buffer)] => Read::read(self, address, buffer),
+        _ => panic!("unsupported transaction"),
+      }
+    }
+  }
+
+  fn noop_raw_waker() -> RawWaker {
+    fn clone(_: *const ()) -> RawWaker {
+      noop_raw_waker()
+    }
+    fn noop(_: *const ()) {}
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    RawWaker::new(core::ptr::null(), &VTABLE)
+  }
+
+  /// Runs a future that never waits, as every mock transaction completes immediately
+  pub fn block_on<F: Future>(future: F) -> F::Output {
+    // Safety: the vtable functions do nothing
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut context = Context::from_waker(&waker);
+    match pin!(future).poll(&mut context) {
+      Poll::Ready(output) => output,
+      Poll::Pending => panic!("mock future is pending"),
+    }
+  }
+}
+
+#[cfg(feature = "async")]
+pub use asynch::block_on;